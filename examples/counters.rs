@@ -0,0 +1,59 @@
+//! An example that shows live event counts in the status line.
+//!
+//! The `CountingLayer` is added to the subscriber next to the formatting layer. It counts every
+//! event by level, and the events of the `example::db` target separately. The counts are read by
+//! the status line through an `EventCounters` instance.
+
+use std::io::Write;
+use std::thread;
+use std::time::Duration;
+
+use crossterm::style::{ContentStyle, Stylize};
+use tracing::{error, info, warn};
+use tracing_subscriber::prelude::*;
+
+use tracing_statusbar::{Builder, CountingLayer, StatusWriter};
+use tracing_statusbar::widgets::{Row, Styled};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Create the counting layer, with a separate count for database events
+    let layer = CountingLayer::with_targets(["example::db"]);
+    let counters = layer.counters();
+
+    // Show the error and warning counts, followed by the number of database events
+    let status = Row::new()
+        .separator(" | ")
+        .push(Styled::new(counters.clone(), ContentStyle::new().yellow()))
+        .push(move |output: &mut StatusWriter| {
+            write!(output, "{} database events", counters.target("example::db").unwrap_or(0))
+        });
+
+    // Create the status line log writer
+    let (writer, _guard) = Builder::with_stdout()
+        .with_callback(status)
+        .threaded()
+        .finish_with_guard();
+
+    // Create a subscriber that combines a formatting layer with the counting layer
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(writer))
+        .with(layer);
+
+    // Set the subscriber as the default
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    // Emit events of various levels and targets
+    for count in 0..30 {
+        match count % 7 {
+            0 => error!(target: "example::db", "Query {count} failed"),
+            1 | 4 => warn!("Request {count} was slow"),
+            2 => info!(target: "example::db::pool", "Opened connection {count}"),
+            _ => info!("Handled request {count}"),
+        }
+
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    info!("All done");
+    Ok(())
+}
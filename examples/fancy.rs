@@ -33,9 +33,8 @@ impl Drop for HiddenCursorGuard {
     }
 }
 
-/// A status line printing callback. This should print the status line to the provided writer and
-/// return the number of newlines written.
-fn write_status_line<W: Write>(output: &mut W) -> io::Result<u16> {
+/// A status line printing callback. This should print the status line to the provided writer.
+fn write_status_line<W: Write>(output: &mut W) -> io::Result<()> {
     // Write the status line. Note that for a single line no newlines should be emitted, so that
     // the status line stays at the bottom of the screen. Also note the use of `queue!` here, which
     // does not flush the output writer. This is done implicitly by the crate.
@@ -46,8 +45,7 @@ fn write_status_line<W: Write>(output: &mut W) -> io::Result<u16> {
         Clear(ClearType::UntilNewLine),
     )?;
 
-    // Return the number of newlines written, which is zero for a single status line.
-    Ok(0)
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -0,0 +1,60 @@
+//! An example using status heartbeats for non-terminal outputs.
+//!
+//! When the output of this example is redirected, e.g. by running it as
+//! `cargo run --example heartbeat | cat`, the status line is printed as an ordinary log line every
+//! two seconds, but only when its text has changed. When run in a terminal the status line is drawn
+//! as usual.
+
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use crossterm::style::{Color, Print, SetForegroundColor};
+use tracing::info;
+
+use tracing_statusbar::{Builder, StatusWriter};
+use tracing_statusbar::widgets::Counter;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let processed = Counter::new();
+
+    // Create the status line log writer. The escape sequences written by the callback are removed
+    // from the heartbeat lines.
+    let writer = Builder::with_stdout()
+        .with_callback({
+            let processed = processed.clone();
+
+            move |output: &mut StatusWriter| -> io::Result<()> {
+                crossterm::queue!(
+                    output,
+                    SetForegroundColor(Color::Green),
+                    Print(format!("--- Processed {} of 20 items ---", processed.get())),
+                )?;
+
+                Ok(())
+            }
+        })
+        .heartbeat(Duration::from_secs(2))
+        .finish_with_threads();
+
+    // Create a subscriber and attach the writer to it
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(writer)
+        .finish();
+
+    // Set the subscriber as the default
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    // Do some work, only logging every few items
+    for item in 0..20 {
+        if item % 5 == 0 {
+            info!("Processing item {item}");
+        }
+
+        thread::sleep(Duration::from_millis(500));
+        processed.inc();
+    }
+
+    info!("All done");
+    Ok(())
+}
@@ -1,8 +1,10 @@
 //! A fully interactive example using terminal raw mode to capture keyboard events.
 //!
 //! This example enters a keyboard reading loop and emits log messages for every key that is
-//! pressed. Pressing escape quits the program. The example uses terminal raw mode to disable the
-//! line buffering that standard input normally has, and disabling local echo.
+//! pressed. Pressing escape quits the program, and pressing `p` makes it panic. A panic hook
+//! restores the terminal in that case. Pressing `h` opens a pager that shows the log history, and
+//! pressing `n` briefly shows a notification below the status line. The example uses terminal raw
+//! mode to disable the line buffering that standard input normally has, and disabling local echo.
 //!
 //! The example uses crossterm's synchronous event reading, but async event reading (by using
 //! `crossterm::event::EventStream`) is also fully supported. For async applications the use of a
@@ -12,6 +14,7 @@
 //! replaced by the stateful or state sharing status bars from the other examples.
 
 use std::io::{self, Write};
+use std::time::Duration;
 
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
 use crossterm::style::Print;
@@ -20,19 +23,17 @@ use tracing::{error, info};
 
 use tracing_statusbar::Builder;
 
-/// A status line printing callback. This should print the status line to the provided writer and
-/// return the number of newlines written.
-fn write_status_line<W: Write>(output: &mut W) -> io::Result<u16> {
+/// A status line printing callback. This should print the status line to the provided writer.
+fn write_status_line<W: Write>(output: &mut W) -> io::Result<()> {
     // Write the status line. Note that for a single line no newlines should be emitted, so that
     // the status line stays at the bottom of the screen. Also note the use of `queue!` here, which
     // does not flush the output writer. This is done implicitly by the crate.
     crossterm::queue!(
         output,
-        Print("--- Press a key to trigger log messages, ESC to quit ---"),
+        Print("--- Press a key to trigger log messages, h for history, ESC to quit ---"),
     )?;
 
-    // Return the number of newlines written, which is zero for a single status line.
-    Ok(0)
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -46,7 +47,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let writer = Builder::with_stdout()
         .with_callback(write_status_line)
         .assume_raw_mode()
-        .finish();
+        .history(1000)
+        .finish_with_threads();
+
+    // Install a panic hook that erases the status line and disables raw mode when panicking
+    writer.install_panic_hook();
+
+    // Keep a handle to the status line, which is used to open the history pager and to show
+    // notifications
+    let handle = writer.handle();
 
     // Create a subscriber and attach the writer to it
     let subscriber = tracing_subscriber::fmt()
@@ -71,6 +80,32 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     break;
                 }
 
+                KeyEvent {
+                    kind: KeyEventKind::Press,
+                    code: KeyCode::Char('p'),
+                    ..
+                } => {
+                    panic!("The p key was pressed");
+                }
+
+                KeyEvent {
+                    kind: KeyEventKind::Press,
+                    code: KeyCode::Char('h'),
+                    ..
+                } => {
+                    // Show the log history until the pager is closed using q or escape
+                    handle.pager()?;
+                }
+
+                KeyEvent {
+                    kind: KeyEventKind::Press,
+                    code: KeyCode::Char('n'),
+                    ..
+                } => {
+                    // Show a notification that disappears after two seconds
+                    handle.notify("Notification shown", Duration::from_secs(2))?;
+                }
+
                 event => {
                     info!("Got a keyboard event: {:?}", event);
                 }
@@ -0,0 +1,51 @@
+//! An example using a width-aware status line layout.
+//!
+//! The layout places segments on the left, in the center, and on the right of the status line,
+//! and pads the line to the width of the terminal. Try resizing the terminal to a narrow width:
+//! the segments with the lowest priority are dropped first, and when nothing else can be dropped
+//! the line is truncated with an ellipsis.
+
+use std::thread;
+use std::time::Duration;
+
+use crossterm::style::{ContentStyle, Stylize};
+use tracing::info;
+
+use tracing_statusbar::Builder;
+use tracing_statusbar::layout::{Layout, Segment};
+use tracing_statusbar::widgets::{Counter, Elapsed, Spinner, Styled, Text};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let messages = Counter::new().label("messages");
+
+    // Create a layout with segments of different priorities
+    let layout = Layout::new()
+        .left(Segment::new(Spinner::new()).priority(2))
+        .left(Segment::new(Text::new("Processing the work queue")).priority(3))
+        .center(Segment::new(Styled::new(messages.clone(), ContentStyle::new().yellow())).priority(1))
+        .right(Elapsed::new());
+
+    // Create the status line log writer
+    let writer = Builder::with_stdout()
+        .with_callback(layout)
+        .refresh_interval(Duration::from_millis(100))
+        .finish_with_threads();
+
+    // Create a subscriber and attach the writer to it
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(writer)
+        .finish();
+
+    // Set the subscriber as the default
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    // Do some work
+    for count in 0..30 {
+        info!("This is log message {count}");
+        messages.inc();
+        thread::sleep(Duration::from_millis(1000));
+    }
+
+    info!("All done");
+    Ok(())
+}
@@ -32,9 +32,8 @@ impl Drop for HiddenCursorGuard {
     }
 }
 
-/// A status line printing callback. This should print the status line to the provided writer and
-/// return the number of newlines written.
-fn write_status_line<W: Write>(output: &mut W) -> io::Result<u16> {
+/// A status line printing callback. This should print the status line to the provided writer.
+fn write_status_line<W: Write>(output: &mut W) -> io::Result<()> {
     // Write the status line. Note that for a multi-line status the last line should not have a
     // newline at the end, so that the status lines stay at the bottom of the screen. Also note the
     // use of `queue!` here, which does not flush the output writer. This is done implicitly by the
@@ -52,8 +51,7 @@ fn write_status_line<W: Write>(output: &mut W) -> io::Result<u16> {
         Print("This is the third line."),
     )?;
 
-    // Return the number of newlines written.
-    Ok(2)
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
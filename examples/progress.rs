@@ -0,0 +1,64 @@
+//! An example that shows a progress line for every running job, similar to a multi-progress bar.
+//!
+//! The `ProgressLayer` is added to the subscriber next to the formatting layer. Every span that
+//! declares a `status.msg`, `status.pos`, or `status.len` field gets its own line in the status
+//! area while it is open, rendered by the `ProgressSpans` widget.
+
+use std::thread;
+use std::time::Duration;
+
+use tracing::{field, info, info_span};
+use tracing_subscriber::prelude::*;
+
+use tracing_statusbar::{Builder, ProgressLayer};
+use tracing_statusbar::widgets::{Lines, Text};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Create the progress layer
+    let layer = ProgressLayer::new();
+
+    // Show the progress lines below a title
+    let status = Lines::new()
+        .push(Text::new("--- Downloads ---"))
+        .push(layer.spans());
+
+    // Create the status line log writer
+    let (writer, _guard) = Builder::with_stdout()
+        .with_callback(status)
+        .threaded()
+        .finish_with_guard();
+
+    // Redraw the status area whenever a progress line changes
+    let layer = layer.redraw(writer.handle());
+
+    // Create a subscriber that combines a formatting layer with the progress layer
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(writer))
+        .with(layer);
+
+    // Set the subscriber as the default
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    // Download some files of various sizes in parallel
+    let jobs: Vec<_> = [40, 25, 60, 15].into_iter().enumerate().map(|(index, length)| {
+        thread::spawn(move || {
+            let name = format!("file-{index}.tar.gz");
+            let span = info_span!("download", status.msg = field::display(&name), status.pos = 0, status.len = length);
+            let _entered = span.enter();
+
+            for position in 1..=length {
+                thread::sleep(Duration::from_millis(100));
+                span.record("status.pos", position);
+            }
+
+            info!("Downloaded {name}");
+        })
+    }).collect();
+
+    for job in jobs {
+        job.join().expect("Download thread paniced");
+    }
+
+    info!("All done");
+    Ok(())
+}
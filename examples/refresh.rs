@@ -0,0 +1,55 @@
+//! An example using a status line that is refreshed periodically.
+//!
+//! The status line in this example shows a spinner and the elapsed time. Log messages are only
+//! written every few seconds, but because a refresh interval is configured the status line keeps
+//! updating in between.
+
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::style::Print;
+use tracing::info;
+
+use tracing_statusbar::{Builder, StatusWriter};
+
+/// The frames of the spinner animation.
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let start = Instant::now();
+
+    // Create the status line log writer. The callback is a closure that captures the start time
+    // so that it can display the elapsed time.
+    let writer = Builder::with_stdout()
+        .with_callback(move |output: &mut StatusWriter| -> io::Result<()> {
+            let elapsed = start.elapsed();
+            let frame = SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()];
+
+            crossterm::queue!(
+                output,
+                Print(format!("{frame} Working for {:.1}s", elapsed.as_secs_f32())),
+            )?;
+
+            Ok(())
+        })
+        .refresh_interval(Duration::from_millis(100))
+        .finish_with_threads();
+
+    // Create a subscriber and attach the writer to it
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(writer)
+        .finish();
+
+    // Set the subscriber as the default
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    // Do some work, with long pauses between log messages
+    for count in 0..5 {
+        info!("This is log message {count}");
+        thread::sleep(Duration::from_millis(3000));
+    }
+
+    info!("All done");
+    Ok(())
+}
@@ -0,0 +1,61 @@
+//! An example that pins the status area to the bottom of the terminal using a scroll region.
+//!
+//! Log messages are written in quick succession. Because the log messages scroll within a region
+//! above the status area, the status area is only repainted when its contents change, instead of
+//! after every log message.
+
+use std::thread;
+use std::time::Duration;
+
+use tracing::info;
+
+use tracing_statusbar::{Builder, RenderMode};
+use tracing_statusbar::widgets::{Counter, Lines, ProgressBar, Row, Text};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Create the widgets that are updated by the program
+    let message = Text::new("Starting up");
+    let lines = Counter::new().label("lines");
+    let progress = ProgressBar::new(2000);
+
+    // Compose the status area from the widgets
+    let status = Lines::new()
+        .push(message.clone())
+        .push(Row::new()
+            .separator(" | ")
+            .push(progress.clone())
+            .push(progress.percent())
+            .push(lines.clone()));
+
+    // Create the status line log writer using a scroll region
+    let writer = Builder::with_stdout()
+        .with_callback(status)
+        .render_mode(RenderMode::ScrollRegion)
+        .threaded()
+        .finish();
+
+    // Create a subscriber and attach the writer to it
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(writer)
+        .finish();
+
+    // Set the subscriber as the default
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    // Write a lot of log messages
+    for count in 0..2000 {
+        if count % 500 == 0 {
+            message.set(format!("Working on batch {}", count / 500 + 1));
+        }
+
+        info!("This is log message {count}");
+        lines.inc();
+        progress.inc(1);
+
+        thread::sleep(Duration::from_millis(2));
+    }
+
+    message.set("Done");
+    info!("All done");
+    Ok(())
+}
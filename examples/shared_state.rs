@@ -5,10 +5,13 @@
 //! a state struct. The newtype is given a `MakeCallback` impl so that it can be passed to the
 //! status line builder.
 //!
+//! Because the status line is normally only redrawn after log messages are written, the example
+//! uses a `StatusHandle` to redraw the status line as soon as the progress value changes.
+//!
 //! Compare also to the `simple_shared_state.rs` example, which provides an alternative to the
 //! `MakeCallback` pattern used here that is more simple but also less flexible.
 
-use std::io::{self, Write};
+use std::io;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
@@ -16,7 +19,7 @@ use std::time::Duration;
 use crossterm::style::Print;
 use tracing::info;
 
-use tracing_statusbar::{Builder, MakeCallback};
+use tracing_statusbar::{Builder, MakeCallback, StatusWriter};
 
 /// A struct that represents the status line state.
 #[derive(Default)]
@@ -37,8 +40,8 @@ struct StatusLine(Arc<Mutex<StatusLineState>>);
 ///
 /// The callback factory takes ownership of self and wraps it into the boxed closure. This allows
 /// access to the shared status line state (via internal mutability).
-impl<W: Write> MakeCallback<W> for StatusLine {
-    type Callback = Box<dyn FnMut(&mut W) -> io::Result<u16> + Send>;
+impl MakeCallback for StatusLine {
+    type Callback = Box<dyn FnMut(&mut StatusWriter) -> io::Result<()> + Send>;
 
     fn make_callback(self) -> Self::Callback {
         Box::new(move |output| {
@@ -57,8 +60,7 @@ impl<W: Write> MakeCallback<W> for StatusLine {
                 Print(format!("--- The statusbar was redrawn {} times, progress is {:.1}% ---", state.count, state.progress)),
             )?;
 
-            // Return the number of newlines written, which is zero for a single status line.
-            Ok(0)
+            Ok(())
         })
     }
 }
@@ -72,6 +74,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_callback(StatusLine(state.clone()))
         .finish();
 
+    // Obtain a handle to the status line so that it can be redrawn after updating the state
+    let status = writer.handle();
+
     // Create a subscriber and attach the writer to it
     let subscriber = tracing_subscriber::fmt()
         .with_writer(writer)
@@ -90,6 +95,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             .lock()
             .expect("Status line mutex was poisoned")
             .progress = ((count + 1) as f32 / 10.0) * 100.0;
+
+        // Show the new progress value right away
+        status.redraw()?;
     }
 
     info!("All done");
@@ -11,7 +11,7 @@
 //! Compare also to the `shared_state.rs` example, which provides an alternative pattern that does
 //! offer full flexibility and generic typing.
 
-use std::io::{self, Stdout, Write};
+use std::io::{self, Write};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
@@ -19,7 +19,7 @@ use std::time::Duration;
 use crossterm::style::Print;
 use tracing::info;
 
-use tracing_statusbar::Builder;
+use tracing_statusbar::{Builder, StatusWriter};
 
 /// A struct that represents the status line and its state.
 #[derive(Default)]
@@ -32,7 +32,7 @@ struct StatusLine {
 }
 
 impl StatusLine {
-    fn render<T: Write>(&mut self, output: &mut T) -> io::Result<u16> {
+    fn render<T: Write>(&mut self, output: &mut T) -> io::Result<()> {
         // Increment the counter
         self.count += 1;
 
@@ -45,8 +45,7 @@ impl StatusLine {
             Print(format!("--- The statusbar was redrawn {} times, progress is {:.1}% ---", self.count, self.progress)),
         )?;
 
-        // Return the number of newlines written, which is zero for a single status line.
-        Ok(0)
+        Ok(())
     }
 }
 
@@ -60,7 +59,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_callback({
             let status_line = status_line.clone();
 
-            move |output: &mut Stdout| {
+            move |output: &mut StatusWriter| {
                 status_line
                     .lock()
                     .expect("Status line mutex was poisoned")
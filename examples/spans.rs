@@ -0,0 +1,72 @@
+//! An example that shows the currently entered spans in the status line.
+//!
+//! The `StatusLayer` is added to the subscriber next to the formatting layer. It tracks which
+//! spans are entered, and shares this information with the status line callback through an
+//! `ActiveSpans` instance.
+
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use crossterm::style::Print;
+use tracing::{info, info_span};
+use tracing_subscriber::prelude::*;
+
+use tracing_statusbar::{ActiveSpans, Builder, MakeCallback, StatusLayer, StatusWriter};
+
+/// A status line that shows the span stack of the most recently active thread.
+struct StatusLine(ActiveSpans);
+
+impl MakeCallback for StatusLine {
+    type Callback = Box<dyn FnMut(&mut StatusWriter) -> io::Result<()> + Send>;
+
+    fn make_callback(self) -> Self::Callback {
+        Box::new(move |output| {
+            let status = match self.0.most_recent() {
+                Some(stack) => format!("--- {stack} ---"),
+                None => "--- Idle ---".to_owned(),
+            };
+
+            crossterm::queue!(
+                output,
+                Print(status),
+            )?;
+
+            Ok(())
+        })
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Create the span tracking layer
+    let layer = StatusLayer::new();
+
+    // Create the status line log writer. A refresh interval is used so that the status line
+    // follows span changes even when no log messages are written.
+    let writer = Builder::with_stdout()
+        .with_callback(StatusLine(layer.spans()))
+        .refresh_interval(Duration::from_millis(100))
+        .finish_with_threads();
+
+    // Create a subscriber that combines a formatting layer with the span tracking layer
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(writer))
+        .with(layer);
+
+    // Set the subscriber as the default
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    // Do some work inside nested spans
+    for krate in ["foo", "bar", "baz"] {
+        let _compiling = info_span!("compiling", krate).entered();
+        info!("Compiling {krate}");
+
+        for phase in ["parsing", "codegen", "linking"] {
+            let _phase = info_span!("phase", phase).entered();
+            thread::sleep(Duration::from_millis(1000));
+        }
+    }
+
+    info!("All done");
+    Ok(())
+}
@@ -3,14 +3,14 @@
 //! This example is similar to the basic unthreaded example, but allows the status line to maintain
 //! state.
 
-use std::io::{self, Write};
+use std::io;
 use std::thread;
 use std::time::Duration;
 
 use crossterm::style::Print;
 use tracing::info;
 
-use tracing_statusbar::{Builder, MakeCallback};
+use tracing_statusbar::{Builder, MakeCallback, StatusWriter};
 
 /// A struct that represents the status line state. It contains a counter that indicates the number
 /// of times the status line callback was invoked.
@@ -27,8 +27,8 @@ struct StatusLine {
 ///
 /// For shared access to the status line the status line struct or its internals can be wrapped
 /// with internal mutability containers.
-impl<W: Write> MakeCallback<W> for StatusLine {
-    type Callback = Box<dyn FnMut(&mut W) -> io::Result<u16> + Send>;
+impl MakeCallback for StatusLine {
+    type Callback = Box<dyn FnMut(&mut StatusWriter) -> io::Result<()> + Send>;
 
     fn make_callback(mut self) -> Self::Callback {
         Box::new(move |output| {
@@ -44,8 +44,7 @@ impl<W: Write> MakeCallback<W> for StatusLine {
                 Print(format!("--- The statusbar was redrawn {} times ---", self.count)),
             )?;
 
-            // Return the number of newlines written, which is zero for a single status line.
-            Ok(0)
+            Ok(())
         })
     }
 }
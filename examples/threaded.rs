@@ -7,24 +7,13 @@ use std::io::{self, Write};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use crossterm::cursor::MoveToColumn;
 use crossterm::style::Print;
-use crossterm::terminal::{Clear, ClearType};
 use tracing::info;
 
 use tracing_statusbar::Builder;
 
-/// A status line printing callback. This should print the status line to the provided writer and
-/// return the number of newlines written.
-fn write_status_line<W: Write>(output: &mut W) -> io::Result<u16> {
-    // Show a temporary status indicating that the callback was fired. Normally the status line
-    // should not be written twice in this callback, but this is just done to illustrate how the
-    // threaded log writer buffers any log messages.
-    crossterm::execute!(
-        output,
-        Print("--- Doing some work ---"),
-    )?;
-
+/// A status line printing callback. This should print the status line to the provided writer.
+fn write_status_line<W: Write>(output: &mut W) -> io::Result<()> {
     // Simulate a long operation. Normally the running time of this callback should be kept short,
     // but the sleep shows that the log writer will not block any other threads, and that logs are
     // buffered.
@@ -35,21 +24,19 @@ fn write_status_line<W: Write>(output: &mut W) -> io::Result<u16> {
     // does not flush the output writer. This is done implicitly by the crate.
     crossterm::queue!(
         output,
-        MoveToColumn(0),
-        Clear(ClearType::CurrentLine),
         Print("--- Waiting for the next log message ---"),
     )?;
 
-    // Return the number of newlines written, which is zero for a single status line.
-    Ok(0)
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Create the status line log writer
-    let writer = Builder::with_stdout()
+    // Create the status line log writer. The guard makes sure that all log messages are written
+    // before the program exits.
+    let (writer, _guard) = Builder::with_stdout()
         .with_callback(write_status_line)
         .threaded()
-        .finish();
+        .finish_with_guard();
 
     // Create a subscriber and attach the writer to it
     let subscriber = tracing_subscriber::fmt()
@@ -0,0 +1,61 @@
+//! An example that writes log messages using a task on a Tokio runtime.
+//!
+//! The log writing task is spawned on the runtime of the program. When Ctrl-C is pressed, or when
+//! the work is done, the program shuts down gracefully by awaiting the guard, which writes all
+//! queued log messages and finalizes the status area.
+
+use std::time::Duration;
+
+use tokio::time;
+use tracing::info;
+
+use tracing_statusbar::Builder;
+use tracing_statusbar::widgets::{Counter, ProgressBar, Row, Spinner};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Create the widgets that are updated by the program
+    let tasks = Counter::new().label("tasks");
+    let progress = ProgressBar::new(50);
+
+    let status = Row::new()
+        .separator(" ")
+        .push(Spinner::new())
+        .push(progress.clone())
+        .push(tasks.clone());
+
+    // Create the status line log writer on the current runtime
+    let (writer, guard) = Builder::with_stdout()
+        .with_callback(status)
+        .refresh_interval(Duration::from_millis(100))
+        .tokio(tokio::runtime::Handle::current())
+        .finish_with_guard();
+
+    // Create a subscriber and attach the writer to it
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(writer)
+        .finish();
+
+    // Set the subscriber as the default
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    // Do some work until it is done or Ctrl-C is pressed
+    let work = async {
+        for count in 0..50 {
+            info!("Finished task {count}");
+            tasks.inc();
+            progress.inc(1);
+
+            time::sleep(Duration::from_millis(100)).await;
+        }
+    };
+
+    tokio::select! {
+        _ = work => info!("All done"),
+        _ = tokio::signal::ctrl_c() => info!("Interrupted, shutting down"),
+    }
+
+    // Write all queued log messages before exiting
+    guard.shutdown().await;
+    Ok(())
+}
@@ -12,9 +12,8 @@ use tracing::info;
 
 use tracing_statusbar::Builder;
 
-/// A status line printing callback. This should print the status line to the provided writer and
-/// return the number of newlines written.
-fn write_status_line<W: Write>(output: &mut W) -> io::Result<u16> {
+/// A status line printing callback. This should print the status line to the provided writer.
+fn write_status_line<W: Write>(output: &mut W) -> io::Result<()> {
     // Write the status line. Note that for a single line no newlines should be emitted, so that
     // the status line stays at the bottom of the screen. Also note the use of `queue!` here, which
     // does not flush the output writer. This is done implicitly by the crate.
@@ -23,8 +22,7 @@ fn write_status_line<W: Write>(output: &mut W) -> io::Result<u16> {
         Print("--- This is the status bar ---"),
     )?;
 
-    // Return the number of newlines written, which is zero for a single status line.
-    Ok(0)
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
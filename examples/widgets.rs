@@ -0,0 +1,77 @@
+//! An example that builds a multi-line status area from ready-made widgets.
+//!
+//! Widgets that show program state are cheap to clone. The clones share their state, so the
+//! program can keep a clone to update the widget while the status line owns the original.
+
+use std::io::Write;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::style::{ContentStyle, Stylize};
+use tracing::info;
+
+use tracing_statusbar::{Builder, FinishMode, StatusWriter};
+use tracing_statusbar::widgets::{Counter, Elapsed, Lines, ProgressBar, Row, Spinner, Styled, Text};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Create the widgets that are updated by the program
+    let message = Text::new("Starting up");
+    let files = Counter::new().label("files");
+    let progress = ProgressBar::new(20);
+
+    // Compose the status area from the widgets
+    let status = Lines::new()
+        .push(Row::new()
+            .push(Styled::new(Spinner::new(), ContentStyle::new().cyan()))
+            .push(message.clone()))
+        .push(Row::new()
+            .separator(" | ")
+            .push(progress.clone())
+            .push(progress.percent())
+            .push(progress.rate())
+            .push(progress.eta())
+            .push(files.clone())
+            .push(Elapsed::new()));
+
+    // Create the status line log writer. A refresh interval keeps the spinner and the elapsed
+    // time up to date. When the guard is dropped at the end of `main` the status area is replaced
+    // by a summary line.
+    let start = Instant::now();
+
+    let (writer, _guard) = Builder::with_stdout()
+        .with_callback(status)
+        .refresh_interval(Duration::from_millis(100))
+        .on_finish(FinishMode::summary({
+            let files = files.clone();
+
+            move |output: &mut StatusWriter| {
+                write!(output, "Finished in {:.1}s, {} files processed", start.elapsed().as_secs_f32(), files.get())
+            }
+        }))
+        .threaded()
+        .finish_with_guard();
+
+    // Create a subscriber and attach the writer to it
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(writer)
+        .finish();
+
+    // Set the subscriber as the default
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    // Do some work
+    for count in 0..20 {
+        message.set(format!("Processing file {count}"));
+        thread::sleep(Duration::from_millis(500));
+
+        files.inc();
+        progress.inc(1);
+
+        if count % 5 == 4 {
+            info!("Processed {} files", count + 1);
+        }
+    }
+
+    info!("All done");
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use unicode_width::UnicodeWidthChar;
+
+/// A piece of terminal output: either an escape sequence or a single character.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Token<'a> {
+    /// An escape sequence, including the leading escape character.
+    Escape(&'a str),
+
+    /// A character that is printed to the terminal.
+    Char(char),
+}
+
+/// An iterator that splits terminal output into tokens.
+pub(crate) struct Tokens<'a> {
+    /// The remaining input.
+    input: &'a str,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chars = self.input.char_indices();
+        let (_, first) = chars.next()?;
+
+        if first != '\x1b' {
+            self.input = &self.input[first.len_utf8()..];
+            return Some(Token::Char(first));
+        }
+
+        let end = match chars.next() {
+            // A control sequence, terminated by a byte in the range 0x40-0x7e
+            Some((_, '[')) => chars
+                .find(|(_, c)| ('\x40'..='\x7e').contains(c))
+                .map(|(index, c)| index + c.len_utf8()),
+
+            // An operating system command, terminated by BEL or ST
+            Some((_, ']')) => {
+                let rest = &self.input[2..];
+
+                rest.find(['\x07', '\x1b'])
+                    .map(|index| match rest[index..].starts_with("\x1b\\") {
+                        true => 2 + index + 2,
+                        false => 2 + index + 1,
+                    })
+            }
+
+            // A two character escape sequence
+            Some((index, c)) => Some(index + c.len_utf8()),
+
+            None => None,
+        };
+
+        let end = end.unwrap_or(self.input.len());
+        let (escape, rest) = self.input.split_at(end);
+        self.input = rest;
+
+        Some(Token::Escape(escape))
+    }
+}
+
+/// Split terminal output into escape sequences and printed characters.
+pub(crate) fn tokens(input: &str) -> Tokens<'_> {
+    Tokens {
+        input,
+    }
+}
+
+/// Return the number of terminal columns a character occupies.
+pub(crate) fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+/// Return the number of terminal columns the provided text occupies, ignoring escape sequences.
+pub(crate) fn width(input: &str) -> usize {
+    tokens(input)
+        .map(|token| match token {
+            Token::Char(c) => char_width(c),
+            Token::Escape(_) => 0,
+        })
+        .sum()
+}
+
+/// Remove all escape sequences and control characters other than newlines from the provided text,
+/// leaving only the printed characters.
+pub(crate) fn strip(input: &str) -> String {
+    tokens(input)
+        .filter_map(|token| match token {
+            Token::Char(c) if c == '\n' || !c.is_control() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Truncate text so that it occupies at most `max_width` columns, ending it with the provided
+/// ellipsis if anything was cut off.
+///
+/// Escape sequences are preserved up to the point of truncation. When text is cut off and the text
+/// contained escape sequences, the result ends with an attribute reset so that the style of the
+/// removed text does not leak into the rest of the line.
+pub(crate) fn truncate(input: &str, max_width: usize, ellipsis: &str) -> String {
+    if width(input) <= max_width {
+        return input.to_owned();
+    }
+
+    let budget = max_width.saturating_sub(width(ellipsis));
+    let mut output = String::new();
+    let mut used = 0;
+    let mut styled = false;
+
+    for token in tokens(input) {
+        match token {
+            Token::Escape(escape) => {
+                output.push_str(escape);
+                styled = true;
+            }
+
+            Token::Char(c) => {
+                if used + char_width(c) > budget {
+                    break;
+                }
+
+                used += char_width(c);
+                output.push(c);
+            }
+        }
+    }
+
+    if styled {
+        output.push_str("\x1b[0m");
+    }
+
+    if max_width >= width(ellipsis) {
+        output.push_str(ellipsis);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_sequences_have_no_width() {
+        assert_eq!(width(""), 0);
+        assert_eq!(width("\x1b[1;31mred\x1b[0m"), 3);
+        assert_eq!(width("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x07"), 4);
+        assert_eq!(width("e\u{301}"), 1);
+        assert_eq!(width("中文"), 4);
+
+        assert_eq!(strip("\x1b[31mred\x1b[0m\tline\n"), "redline\n");
+        assert_eq!(strip("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\"), "link");
+    }
+
+    #[test]
+    fn truncation_keeps_escape_sequences_whole() {
+        // Text that fits is left alone
+        assert_eq!(truncate("\x1b[31mred\x1b[0m", 3, "…"), "\x1b[31mred\x1b[0m");
+
+        // The cut lands inside a styled part, so the style is reset
+        assert_eq!(truncate("ab\x1b[31mcd\x1b[0mef", 3, ""), "ab\x1b[31mc\x1b[0m");
+
+        // The cut lands right before an escape sequence, which is kept but has no effect
+        assert_eq!(truncate("ab\x1b[31mcd", 2, ""), "ab\x1b[31m\x1b[0m");
+
+        // An unterminated escape sequence is treated as a single token
+        assert_eq!(truncate("abc\x1b[31", 2, ""), "ab");
+    }
+
+    #[test]
+    fn wide_characters_are_not_split() {
+        assert_eq!(truncate("a中b", 2, ""), "a");
+        assert_eq!(truncate("中文字", 4, "…"), "中…");
+        assert_eq!(truncate("中文字", 3, "…"), "中…");
+        assert_eq!(truncate("中文字", 2, "…"), "…");
+    }
+
+    #[test]
+    fn zero_width_drops_everything() {
+        assert_eq!(truncate("abc", 0, "…"), "");
+        assert_eq!(truncate("\x1b[1mabc", 0, ""), "\x1b[1m\x1b[0m");
+        assert_eq!(truncate("", 0, "…"), "");
+    }
+}
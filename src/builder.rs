@@ -1,7 +1,18 @@
-use std::io::{self, Stdout, Write};
-use std::marker::PhantomData;
+use std::env;
+use std::io::{self, IsTerminal, Stderr, Stdout, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use crate::{ThreadedHandler, UnthreadedHandler};
+use crossterm::style::ContentStyle;
+use tracing::Level;
+
+use crate::{DropCounter, ErrorPolicy, FinishMode, OverflowPolicy, RenderMode, StatusWriter, SynchronizedOutput};
+use crate::{ThreadedHandler, UnthreadedHandler, WorkerGuard};
+use crate::error::HealthState;
+use crate::renderer::Route;
+
+#[cfg(feature = "tokio")]
+use crate::{TokioGuard, TokioHandler};
 
 /// The `MakeCallback` trait is used to create status line callbacks.
 ///
@@ -11,13 +22,13 @@ use crate::{ThreadedHandler, UnthreadedHandler};
 /// a callback, including any captured shared state.
 ///
 /// See the crate's examples directory for examples on how to implement these patterns.
-pub trait MakeCallback<W: Write> {
+pub trait MakeCallback {
     /// The type of the status line callback. This must be a callable that takes a mutable
-    /// reference to a writer and returns a result containing the number of newlines emitted.
+    /// reference to a `StatusWriter` and returns a result.
     ///
     /// The use of threaded status line handlers may add additional constraints on the callback
     /// type.
-    type Callback: (FnMut(&mut W) -> io::Result<u16>);
+    type Callback: (FnMut(&mut StatusWriter) -> io::Result<()>);
 
     /// Create the status line callback. This consumes self and converts it into a suitable
     /// callback.
@@ -29,10 +40,9 @@ pub trait MakeCallback<W: Write> {
 ///
 /// The blanket impl is provided so that the status line builder can be called with a function or
 /// closure as an argument, allowing for a simplified API for some use cases.
-impl<T, W> MakeCallback<W> for T
+impl<T> MakeCallback for T
 where
-    T: FnMut(&mut W) -> io::Result<u16>,
-    W: Write,
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
 {
     type Callback = Self;
 
@@ -50,6 +60,10 @@ mod private {
 /// Typestate trait for `Builder`.
 pub trait State: private::Sealed {}
 
+/// Typestate trait for `Builder` states that send log messages to a background writer through a
+/// queue.
+pub trait Queued: State {}
+
 /// An uninitialized state for `Builder`. This indicates a builder that does not yet have a status
 /// line draw callback assigned to it.
 pub struct Uninitialized;
@@ -59,77 +73,185 @@ impl private::Sealed for Uninitialized {}
 
 /// A state for `Builder` that will initialize the log handler with log writing on a background
 /// thread.
-pub struct Threaded<T, W>
-where
-    T: MakeCallback<W>,
-    W: Write,
-{
+pub struct Threaded<T: MakeCallback> {
     callback: T,
-    _marker: PhantomData<W>,
 }
 
-impl<T, W> Threaded<T, W>
-where
-    T: MakeCallback<W>,
-    W: Write,
-{
+impl<T: MakeCallback> Threaded<T> {
     /// Initialize a new threaded state using the provided callback.
     fn new(callback: T) -> Self {
         Self {
             callback,
-            _marker: PhantomData,
         }
     }
 }
 
-impl<T, W> State for Threaded<T, W>
-where
-    T: MakeCallback<W>,
-    W: Write,
-{}
+impl<T: MakeCallback> State for Threaded<T> {}
+impl<T: MakeCallback> Queued for Threaded<T> {}
+impl<T: MakeCallback> private::Sealed for Threaded<T> {}
 
-impl<T, W> private::Sealed for Threaded<T, W>
-where
-    T: MakeCallback<W>,
-    W: Write,
-{}
+/// A state for `Builder` that will initialize the log handler with log writing on a task of a
+/// Tokio runtime.
+#[cfg(feature = "tokio")]
+pub struct Tokio<T: MakeCallback> {
+    callback: T,
+    runtime: tokio::runtime::Handle,
+}
+
+#[cfg(feature = "tokio")]
+impl<T: MakeCallback> Tokio<T> {
+    /// Initialize a new Tokio state using the provided callback and runtime.
+    fn new(callback: T, runtime: tokio::runtime::Handle) -> Self {
+        Self {
+            callback,
+            runtime,
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: MakeCallback> State for Tokio<T> {}
+#[cfg(feature = "tokio")]
+impl<T: MakeCallback> Queued for Tokio<T> {}
+#[cfg(feature = "tokio")]
+impl<T: MakeCallback> private::Sealed for Tokio<T> {}
 
 /// A state for `Builder` that will initialize the log handler with log writing on the foreground
 /// thread.
-pub struct Unthreaded<T, W>
-where
-    T: MakeCallback<W>,
-    W: Write,
-{
+pub struct Unthreaded<T: MakeCallback> {
     callback: T,
-    _marker: PhantomData<W>,
 }
 
-impl<T, W> Unthreaded<T, W>
-where
-    T: MakeCallback<W>,
-    W: Write,
-{
+impl<T: MakeCallback> Unthreaded<T> {
     /// Initialize a new unthreaded state using the provided callback.
     fn new(callback: T) -> Self {
         Self {
             callback,
-            _marker: PhantomData,
         }
     }
 }
 
-impl<T, W> State for Unthreaded<T, W>
-where
-    T: MakeCallback<W>,
-    W: Write,
-{}
+impl<T: MakeCallback> State for Unthreaded<T> {}
+impl<T: MakeCallback> private::Sealed for Unthreaded<T> {}
 
-impl<T, W> private::Sealed for Unthreaded<T, W>
-where
-    T: MakeCallback<W>,
-    W: Write,
-{}
+/// The settings shared by all kinds of log handlers.
+pub(crate) struct Settings {
+    /// When true the output is assumed to be a terminal in raw mode.
+    pub assume_raw_mode: bool,
+
+    /// The interval at which the status line is redrawn when no log messages arrive.
+    pub refresh_interval: Option<Duration>,
+
+    /// The maximum number of log entries that can be queued for a threaded handler.
+    pub channel_capacity: usize,
+
+    /// What to do when the queue of a threaded handler is full.
+    pub overflow_policy: OverflowPolicy,
+
+    /// The counter that tracks the number of log entries discarded by the overflow policy.
+    pub drop_counter: DropCounter,
+
+    /// A fixed terminal size, as columns and rows. The size is queried from the terminal when
+    /// this is `None`.
+    pub terminal_size: Option<(u16, u16)>,
+
+    /// Whether the output writer was detected to be a terminal.
+    pub is_terminal: bool,
+
+    /// Overrides the terminal detection when set.
+    pub force_terminal: Option<bool>,
+
+    /// The interval at which the status is printed as a plain log line when the output is not a
+    /// terminal.
+    pub heartbeat: Option<Duration>,
+
+    /// The way in which the status area is drawn.
+    pub render_mode: RenderMode,
+
+    /// Whether frames are wrapped in synchronized updates.
+    pub synchronized_output: SynchronizedOutput,
+
+    /// What to do when writing to the output or invoking the status line callback fails.
+    pub error_policy: ErrorPolicy,
+
+    /// The health of the log handler, shared between the renderer and the handler.
+    pub health: Arc<HealthState>,
+
+    /// What to do with the status area when the log handler shuts down.
+    pub on_finish: FinishMode,
+
+    /// The styles applied to log entries of specific levels.
+    pub level_styles: Vec<(Level, ContentStyle)>,
+
+    /// The secondary writers for log entries of selected targets.
+    pub routes: Vec<Route>,
+
+    /// The number of log entries kept in memory for the history pager.
+    pub history: usize,
+
+    /// Whether consecutive identical log entries are collapsed into a single line.
+    pub collapse_repeats: bool,
+
+    /// The maximum number of log entries per callsite, and the interval over which they are
+    /// counted.
+    pub rate_limit: Option<(u32, Duration)>,
+
+    /// The maximum number of rows of the status area. The status area is always limited to the
+    /// height of the terminal as well.
+    pub max_status_lines: Option<usize>,
+}
+
+impl Settings {
+    /// Return the interval at which the handler should invoke the renderer when no log messages
+    /// arrive. This is the refresh interval for terminals, and the heartbeat interval otherwise.
+    pub fn tick_interval(&self) -> Option<Duration> {
+        match self.terminal() {
+            true => self.refresh_interval,
+            false => self.heartbeat,
+        }
+    }
+
+    /// Return true when the status line should be drawn, i.e. when the output is a terminal that
+    /// supports cursor movement.
+    ///
+    /// When this returns false log messages are passed through without any escape sequences.
+    pub fn terminal(&self) -> bool {
+        self.force_terminal.unwrap_or_else(|| self.is_terminal && !is_dumb_terminal())
+    }
+}
+
+/// Return true when the `TERM` environment variable indicates a terminal that does not support
+/// cursor movement.
+fn is_dumb_terminal() -> bool {
+    env::var_os("TERM").is_some_and(|term| term == "dumb")
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            assume_raw_mode: false,
+            refresh_interval: None,
+            channel_capacity: 1024,
+            overflow_policy: OverflowPolicy::default(),
+            drop_counter: DropCounter::default(),
+            terminal_size: None,
+            is_terminal: true,
+            force_terminal: None,
+            heartbeat: None,
+            render_mode: RenderMode::default(),
+            synchronized_output: SynchronizedOutput::default(),
+            error_policy: ErrorPolicy::default(),
+            health: Arc::default(),
+            on_finish: FinishMode::default(),
+            level_styles: Vec::new(),
+            routes: Vec::new(),
+            history: 0,
+            collapse_repeats: false,
+            rate_limit: None,
+            max_status_lines: None,
+        }
+    }
+}
 
 /// A builder struct for status line log writers.
 pub struct Builder<T, W>
@@ -139,25 +261,55 @@ where
 {
     callback: T,
     output: W,
-    assume_raw_mode: bool,
+    settings: Settings,
 }
 
 impl<W: Write> Builder<Uninitialized, W> {
     /// Initialize a new builder for the provided output writer.
+    ///
+    /// The writer is assumed to be a terminal, unless the `TERM` environment variable is set to
+    /// `dumb`. Use `detect` for writers that can tell whether they are a terminal, or
+    /// `force_terminal` to override this.
     pub fn new(output: W) -> Self {
         Self {
             callback: Uninitialized,
             output,
-            assume_raw_mode: false,
+            settings: Settings::default(),
         }
     }
+}
+
+impl<W: Write + IsTerminal> Builder<Uninitialized, W> {
+    /// Initialize a new builder for the provided output writer, detecting whether it is a terminal.
+    ///
+    /// When the writer is not a terminal, e.g. a file or a standard stream that is redirected to a
+    /// file or a pipe, the status line is disabled and log messages are passed through unmodified.
+    pub fn detect(output: W) -> Self {
+        let is_terminal = output.is_terminal();
 
+        let mut builder = Self::new(output);
+        builder.settings.is_terminal = is_terminal;
+        builder
+    }
 }
 
 impl Builder<Uninitialized, Stdout> {
     /// Initialize a new builder using standard output for writing.
+    ///
+    /// When standard output is not a terminal, e.g. because it is redirected to a file or a pipe,
+    /// the status line is disabled and log messages are passed through unmodified.
     pub fn with_stdout() -> Self {
-        Self::new(io::stdout())
+        Self::detect(io::stdout())
+    }
+}
+
+impl Builder<Uninitialized, Stderr> {
+    /// Initialize a new builder using standard error for writing.
+    ///
+    /// When standard error is not a terminal, e.g. because it is redirected to a file or a pipe,
+    /// the status line is disabled and log messages are passed through unmodified.
+    pub fn with_stderr() -> Self {
+        Self::detect(io::stderr())
     }
 }
 
@@ -177,26 +329,27 @@ where
     /// This callback will be invoked every time after writing log messages so that the status line
     /// can be shown again.
     ///
-    /// The callback is provided with a Write impl that should be used for writing log messages. It
-    /// is not advised to use print/println, but instead write directly into the provided writer.
+    /// The callback is provided with a `StatusWriter` that should be used for writing the status
+    /// line. It is not advised to use print/println, but instead write directly into the provided
+    /// writer.
     ///
     /// The recommended approach is to use crossterm or a similar crate for writing. Placement of
     /// the cursor and cleanup of any previously written status lines is handled automatically and
     /// does not need to be taken care of by the callback.
     ///
-    /// The callback should return a result. The success value of the result must represent the
-    /// number of newlines written. A single status line should not write any newlines. Therefore
-    /// the returned value should always be the number of status lines shown minus one.
+    /// A single status line should not end with a newline, and neither should the last line of a
+    /// multi-line status area. The number of terminal rows taken up by the status area is measured
+    /// automatically, including lines that wrap because they are wider than the terminal.
     ///
-    /// The callback does not have to flush the output writer, this is done automatically.
-    pub fn with_callback<C>(self, callback: C) -> Builder<Unthreaded<C, W>, W>
+    /// The callback does not have to flush the writer, this is done automatically.
+    pub fn with_callback<C>(self, callback: C) -> Builder<Unthreaded<C>, W>
     where
-        C: MakeCallback<W>,
+        C: MakeCallback,
     {
         Builder {
             callback: Unthreaded::new(callback),
             output: self.output,
-            assume_raw_mode: self.assume_raw_mode,
+            settings: self.settings,
         }
     }
 
@@ -216,14 +369,211 @@ where
     /// disabling raw mode). Therefore the use of raw mode is not recommended with threaded
     /// handlers.
     pub fn assume_raw_mode(mut self) -> Self {
-        self.assume_raw_mode = true;
+        self.settings.assume_raw_mode = true;
+        self
+    }
+
+    /// Redraw the status line periodically, even when no log messages are written.
+    ///
+    /// By default the status line is only redrawn after writing log messages. This means that
+    /// status lines showing a spinner, a clock, or an elapsed time will appear frozen when the
+    /// application stops logging for a while. Setting a refresh interval ensures that the status
+    /// line callback is invoked at least once per interval.
+    ///
+    /// Threaded handlers redraw the status line from their background thread. Unthreaded handlers
+    /// created by `finish_with_threads` spawn a small companion thread that takes the writer lock
+    /// and redraws the status line. The refresh interval has no effect on unthreaded handlers
+    /// created by `finish`.
+    pub fn refresh_interval(mut self, interval: Duration) -> Self {
+        self.settings.refresh_interval = Some(interval);
+        self
+    }
+
+    /// Use a fixed terminal size instead of querying the size of the terminal.
+    ///
+    /// The terminal size is used to determine how many rows the status area occupies when its
+    /// lines are wider than the terminal. By default it is queried every time the status line is
+    /// drawn. A fixed size is useful when the output writer is not the program's terminal.
+    pub fn terminal_size(mut self, columns: u16, rows: u16) -> Self {
+        self.settings.terminal_size = Some((columns, rows));
+        self
+    }
+
+    /// Override the detection of terminal outputs.
+    ///
+    /// By default the status line is only drawn when the output is a terminal and the `TERM`
+    /// environment variable is not set to `dumb`. Otherwise the handler falls back to plain log
+    /// passthrough: log messages are written without any escape sequences, and the status line
+    /// callback is never invoked.
+    ///
+    /// Passing `true` always draws the status line, passing `false` always uses plain passthrough.
+    pub fn force_terminal(mut self, terminal: bool) -> Self {
+        self.settings.force_terminal = Some(terminal);
+        self
+    }
+
+    /// Print the status as an ordinary log line every `interval` when the output is not a
+    /// terminal.
+    ///
+    /// Without a heartbeat the status line is not shown at all in plain passthrough mode, e.g.
+    /// when the output is redirected to a file. With a heartbeat the status line callback is
+    /// invoked periodically, its output is stripped of escape sequences, and the resulting text is
+    /// written as a log line when it differs from the previously printed status. This allows the
+    /// same status line callback to be used for both terminal and non-terminal outputs.
+    ///
+    /// The heartbeat has no effect when the status line is drawn on a terminal. Unthreaded handlers
+    /// created by `finish` only print heartbeats along with log messages.
+    pub fn heartbeat(mut self, interval: Duration) -> Self {
+        self.settings.heartbeat = Some(interval);
+        self
+    }
+
+    /// Set the way in which the status area is kept below the log messages.
+    ///
+    /// The default is `RenderMode::Inline`, which erases and redraws the status area around every
+    /// batch of log messages. `RenderMode::ScrollRegion` pins the status area to the bottom of the
+    /// terminal instead, which avoids flicker under heavy logging but requires a terminal that
+    /// supports scroll regions.
+    pub fn render_mode(mut self, mode: RenderMode) -> Self {
+        self.settings.render_mode = mode;
+        self
+    }
+
+    /// Set whether each redraw is wrapped in a synchronized update (DEC private mode 2026).
+    ///
+    /// Every redraw is written to the output as a single frame. With synchronized updates the
+    /// terminal also postpones rendering until the frame is complete, which prevents tearing on
+    /// fast terminals. The default is `SynchronizedOutput::Auto`, which enables synchronized
+    /// updates for terminals that are known to support them.
+    pub fn synchronized_output(mut self, synchronized: SynchronizedOutput) -> Self {
+        self.settings.synchronized_output = synchronized;
+        self
+    }
+
+    /// Set the policy that determines what happens when writing to the output or invoking the
+    /// status line callback fails.
+    ///
+    /// The default policy is `ErrorPolicy::DisableStatus`, which stops drawing the status line but
+    /// keeps writing log messages. Errors are never returned to `tracing_subscriber`, because it
+    /// discards them. Use the `health` method of the log handler to check whether errors occurred.
+    pub fn on_error(mut self, policy: ErrorPolicy) -> Self {
+        self.settings.error_policy = policy;
+        self
+    }
+
+    /// Set what happens to the status area when the log handler shuts down.
+    ///
+    /// The default is `FinishMode::Keep`, which leaves the last status area in place and moves the
+    /// cursor to a fresh line below it. `FinishMode::Clear` erases the status area, and
+    /// `FinishMode::Summary` replaces it with a final line rendered by a separate closure.
+    ///
+    /// Threaded handlers finish when they are dropped, or when the guard returned by
+    /// `finish_with_guard` is dropped. Unthreaded handlers finish when they are dropped.
+    pub fn on_finish(mut self, mode: FinishMode) -> Self {
+        self.settings.on_finish = mode;
+        self
+    }
+
+    /// Apply a style to every log message of the provided level, e.g. to show errors in red.
+    ///
+    /// The style is only applied when the status line is drawn on a terminal. Escape sequences in
+    /// the log message itself, such as the colored level emitted by `tracing_subscriber`, may
+    /// reset the style halfway through the message. Consider disabling ANSI output on the
+    /// formatting layer when styling entire messages.
+    ///
+    /// Levels are only known for log messages written through `MakeWriter::make_writer_for`, which
+    /// `tracing_subscriber`'s formatting layer uses.
+    pub fn level_style(mut self, level: Level, style: ContentStyle) -> Self {
+        self.settings.level_styles.retain(|(styled, _)| *styled != level);
+        self.settings.level_styles.push((level, style));
+        self
+    }
+
+    /// Write the log messages of events whose target matches the provided prefix to a secondary
+    /// writer, instead of the status line handler's output.
+    ///
+    /// A prefix matches a target when it is equal to the target, or when the target is a module
+    /// below it. For example, the prefix `hyper` matches `hyper` and `hyper::client`, but not
+    /// `hyper_util`. When several prefixes match, the first route that was added is used. Routed
+    /// log messages are written as is, without any styling.
+    ///
+    /// Targets are only known for log messages written through `MakeWriter::make_writer_for`,
+    /// which `tracing_subscriber`'s formatting layer uses. Errors of the secondary writer are
+    /// handled according to the error policy.
+    pub fn route<R>(mut self, prefix: impl Into<String>, output: R) -> Self
+    where
+        R: Write + Send + 'static,
+    {
+        self.settings.routes.push(Route {
+            prefix: prefix.into(),
+            output: Arc::new(Mutex::new(output)),
+        });
+
+        self
+    }
+
+    /// Keep the most recent log messages in memory, so that they can be browsed using
+    /// `StatusHandle::pager` after they scrolled out of the terminal.
+    ///
+    /// At most `entries` log messages are kept, after which the oldest message is discarded for
+    /// every new one. Messages are stored without escape sequences. Routed log messages are not
+    /// kept. The history is disabled by default.
+    pub fn history(mut self, entries: usize) -> Self {
+        self.settings.history = entries;
+        self
+    }
+
+    /// Collapse consecutive identical log messages into a single line that ends with
+    /// `(repeated N times)`.
+    ///
+    /// The line is updated in place for every repeat, so a retry loop that logs the same message
+    /// over and over again takes up a single line instead of pushing everything else out of view.
+    /// Log messages repeat the previous one when they were emitted by the same callsite and are
+    /// equal apart from escape sequences and the timestamp at their start, so the default format
+    /// of the formatting layer can be used as is. The line shows the most recent timestamp.
+    /// Repeats are not kept in the history.
+    ///
+    /// Collapsing only happens when the status line is drawn on a terminal. Other outputs receive
+    /// every log message.
+    pub fn collapse_repeats(mut self) -> Self {
+        self.settings.collapse_repeats = true;
+        self
+    }
+
+    /// Write at most `messages` log messages per callsite within each `interval`, discarding the
+    /// rest.
+    ///
+    /// Once the interval in which messages were discarded has passed, a note such as
+    /// `suppressed 12 similar messages from app::net` is written with the level and target of
+    /// the callsite. The note is written along with the next log message, when the status line is
+    /// next redrawn, or when the handler shuts down, whichever comes first. Configure a refresh
+    /// interval so that notes appear promptly when no other log messages are written.
+    ///
+    /// Callsites are only known for log messages written through `MakeWriter::make_writer_for`,
+    /// which `tracing_subscriber`'s formatting layer uses. Other log messages are never limited.
+    pub fn rate_limit(mut self, messages: u32, interval: Duration) -> Self {
+        self.settings.rate_limit = Some((messages, interval));
+        self
+    }
+
+    /// Limit the status area to at most `lines` terminal rows, counting wrapped lines as multiple
+    /// rows.
+    ///
+    /// When the status line callback writes more lines than fit, the last rows are replaced with a
+    /// single `+N more` line, where `N` is the number of lines that were cut. Notifications count
+    /// towards the limit as well. Regardless of this setting the status area is always limited to
+    /// the height of the terminal minus one row, so that the previous status area can always be
+    /// erased completely and at least one log message stays visible. The status area is only
+    /// limited by the terminal height by default.
+    pub fn max_status_lines(mut self, lines: usize) -> Self {
+        self.settings.max_status_lines = Some(lines);
         self
     }
 }
 
-impl<T, W> Builder<Unthreaded<T, W>, W>
+impl<T, W> Builder<Unthreaded<T>, W>
 where
-    T: MakeCallback<W> + Send + 'static,
+    T: MakeCallback + Send + 'static,
     W: Write + Send + 'static,
 {
     /// Tell the builder to create a log handler that writes its log messagse using a background
@@ -240,18 +590,91 @@ where
     /// when the disabling of the raw mode races with a pending log message, or when writing a log
     /// message after manually disabling raw mode). Therefore the use of raw mode is not
     /// recommended with threaded handlers.
-    pub fn threaded(self) -> Builder<Threaded<T, W>, W> {
+    pub fn threaded(self) -> Builder<Threaded<T>, W> {
         Builder {
             callback: Threaded::new(self.callback.callback),
             output: self.output,
-            assume_raw_mode: self.assume_raw_mode,
+            settings: self.settings,
+        }
+    }
+
+    /// Tell the builder to create a log handler that writes its log messages using a task on the
+    /// provided Tokio runtime, instead of a background thread.
+    ///
+    /// This requires that the provided status line callback and writer implement `Send + 'static`.
+    /// Log messages are batched in the same way as with a background thread. Batches are written on
+    /// the runtime's blocking thread pool, so that a slow output, or the delays between attempts of
+    /// `ErrorPolicy::Retry`, do not block the runtime's worker threads.
+    ///
+    /// The panic hook can not write the panic message when a task on a `current_thread` runtime
+    /// panics, because the log writing task can not run until the hook returns. See
+    /// `TokioHandler::install_panic_hook`.
+    ///
+    /// The runtime must have the time driver enabled when a refresh interval or a heartbeat is
+    /// configured.
+    ///
+    /// The overflow policy defaults to `OverflowPolicy::DropOldest` for Tokio handlers, because
+    /// log messages are usually emitted from tasks on the same runtime that runs the log writing
+    /// task. Blocking those tasks while the queue is full can keep the log writing task from ever
+    /// running, e.g. on a `current_thread` runtime, which deadlocks the program. Do not use
+    /// `OverflowPolicy::Block` unless log messages are never emitted from the runtime's threads.
+    #[cfg(feature = "tokio")]
+    pub fn tokio(mut self, runtime: tokio::runtime::Handle) -> Builder<Tokio<T>, W> {
+        self.settings.overflow_policy = OverflowPolicy::DropOldest;
+
+        Builder {
+            callback: Tokio::new(self.callback.callback, runtime),
+            output: self.output,
+            settings: self.settings,
         }
     }
 }
 
-impl<T, W> Builder<Threaded<T, W>, W>
+impl<T, W> Builder<T, W>
+where
+    T: Queued,
+    W: Write,
+{
+    /// Set the maximum number of log messages that can be queued for the background writer.
+    ///
+    /// When the queue is full the overflow policy determines what happens to new log messages. The
+    /// default capacity is 1024 messages. The capacity also limits the number of queued log
+    /// messages that are written at once, even when the overflow policy is
+    /// `OverflowPolicy::Unbounded`.
+    pub fn channel_capacity(mut self, capacity: usize) -> Self {
+        self.settings.channel_capacity = capacity;
+        self
+    }
+
+    /// Set the policy that determines what happens when a log message is written while the queue
+    /// for the background writer is full.
+    ///
+    /// The default policy is `OverflowPolicy::Block`, which blocks the logging thread until there
+    /// is room in the queue. In async contexts it may be preferable to drop log messages instead,
+    /// so that a slow terminal cannot block the async runtime's worker threads. Tokio handlers
+    /// default to `OverflowPolicy::DropOldest` for that reason, see `Builder::tokio`.
+    ///
+    /// When log messages are dropped a `[N log lines dropped]` marker is written along with the
+    /// next batch of log messages.
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.settings.overflow_policy = policy;
+        self
+    }
+
+    /// Use the provided counter to track the number of log messages that were dropped because of
+    /// the overflow policy.
+    ///
+    /// The counter can be cloned into the status line callback's state, so that the status line
+    /// can show the number of dropped messages.
+    pub fn drop_counter(mut self, counter: DropCounter) -> Self {
+        self.settings.drop_counter = counter;
+        self
+    }
+}
+
+impl<T, W> Builder<Threaded<T>, W>
 where
-    T: MakeCallback<W> + Send + 'static,
+    T: MakeCallback + Send + 'static,
     W: Write + Send + 'static,
 {
     /// Finish construction of the log handler and return a `MakeWriter` impl.
@@ -261,24 +684,153 @@ where
         ThreadedHandler::new(
             self.callback.callback,
             self.output,
-            self.assume_raw_mode,
+            self.settings,
+        )
+    }
+
+    /// Finish construction of the log handler and return a `MakeWriter` impl, along with a guard
+    /// that owns the background thread.
+    ///
+    /// This is intended for use with `tracing::subscriber::set_global_default`, where the handler
+    /// is never dropped. Dropping the guard at the end of `main` writes all queued log messages,
+    /// finalizes the status area, and joins the background thread, so that no log messages are
+    /// lost when the program exits.
+    pub fn finish_with_guard(self) -> (ThreadedHandler, WorkerGuard) {
+        let mut handler = self.finish();
+        let guard = handler.guard();
+
+        (handler, guard)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T, W> Builder<Tokio<T>, W>
+where
+    T: MakeCallback + Send + 'static,
+    T::Callback: Send + 'static,
+    W: Write + Send + 'static,
+{
+    /// Finish construction of the log handler and return a `MakeWriter` impl.
+    ///
+    /// This can be passed to `with_writer` on a `tracing_subscriber::fmt::SubscriberBuilder`.
+    pub fn finish(self) -> TokioHandler {
+        TokioHandler::new(
+            self.callback.callback,
+            self.callback.runtime,
+            self.output,
+            self.settings,
         )
     }
+
+    /// Finish construction of the log handler and return a `MakeWriter` impl, along with a guard
+    /// that owns the writer task.
+    ///
+    /// Call `TokioGuard::shutdown` in the graceful shutdown path of the program to write all
+    /// queued log messages and finalize the status area.
+    pub fn finish_with_guard(self) -> (TokioHandler, TokioGuard) {
+        let mut handler = self.finish();
+        let guard = handler.guard();
+
+        (handler, guard)
+    }
 }
 
-impl<T, W> Builder<Unthreaded<T, W>, W>
+impl<T, W> Builder<Unthreaded<T>, W>
 where
-    T: MakeCallback<W>,
+    T: MakeCallback,
     W: Write,
 {
     /// Finish construction of the log handler and return a `MakeWriter` impl.
     ///
     /// This can be passed to `with_writer` on a `tracing_subscriber::fmt::SubscriberBuilder`.
+    ///
+    /// The handler does not use any threads, so the status line is only redrawn after writing log
+    /// messages. The refresh interval is not applied, heartbeats are only printed along with log
+    /// messages, and the status area is not redrawn when the terminal is resized. Use
+    /// `finish_with_threads` when the status line callback and writer implement `Send + 'static`.
     pub fn finish(self) -> UnthreadedHandler<T::Callback, W> {
         UnthreadedHandler::new(
             self.callback.callback.make_callback(),
             self.output,
-            self.assume_raw_mode,
+            self.settings,
         )
     }
 }
+
+impl<T, W> Builder<Unthreaded<T>, W>
+where
+    T: MakeCallback,
+    T::Callback: Send + 'static,
+    W: Write + Send + 'static,
+{
+    /// Finish construction of the log handler and return a `MakeWriter` impl, along with the
+    /// companion threads that redraw the status line when no log messages arrive.
+    ///
+    /// This can be passed to `with_writer` on a `tracing_subscriber::fmt::SubscriberBuilder`.
+    ///
+    /// Log messages are still written on the thread that emits them. The companion threads apply
+    /// the refresh interval and heartbeats, and redraw the status area when the terminal is
+    /// resized. This requires that the status line callback and writer implement `Send + 'static`.
+    pub fn finish_with_threads(self) -> UnthreadedHandler<T::Callback, W> {
+        UnthreadedHandler::with_threads(
+            self.callback.callback.make_callback(),
+            self.output,
+            self.settings,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::fs::{self, File};
+    use std::process;
+    use std::rc::Rc;
+
+    use tracing_subscriber::fmt::MakeWriter;
+
+    use super::*;
+    use crate::testing::VirtualTerminal;
+
+    #[test]
+    fn redirected_outputs_are_not_terminals() {
+        let path = env::temp_dir().join(format!("tracing-statusbar-{}.log", process::id()));
+        let file = File::create(&path).unwrap();
+
+        let handler = Builder::detect(file)
+            .with_callback(|output: &mut StatusWriter| write!(output, "status"))
+            .finish();
+
+        handler.make_writer().write_all(b"first\n").unwrap();
+        drop(handler);
+
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // No status line and no escape sequences
+        assert_eq!(contents, "first\n");
+    }
+
+    #[test]
+    fn unthreaded_handlers_accept_callbacks_that_are_not_send() {
+        let terminal = VirtualTerminal::new(20, 5);
+        let count = Rc::new(Cell::new(0));
+
+        let handler = Builder::new(terminal.clone())
+            .with_callback({
+                let count = count.clone();
+
+                move |output: &mut StatusWriter| {
+                    count.set(count.get() + 1);
+                    write!(output, "status {}", count.get())
+                }
+            })
+            .terminal_size(20, 5)
+            .force_terminal(true)
+            .finish();
+
+        handler.make_writer().write_all(b"first\n").unwrap();
+
+        assert_eq!(terminal.screen(), ["first", "status 1", "", "", ""]);
+    }
+}
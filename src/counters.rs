@@ -0,0 +1,207 @@
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+
+use crate::{StatusWriter, utils};
+use crate::widgets::{Widget, impl_make_callback};
+
+/// The levels in the order in which their counts are stored.
+const LEVELS: [Level; 5] = [Level::ERROR, Level::WARN, Level::INFO, Level::DEBUG, Level::TRACE];
+
+/// Return the index of the count of the provided level.
+fn level_index(level: &Level) -> usize {
+    match *level {
+        Level::ERROR => 0,
+        Level::WARN => 1,
+        Level::INFO => 2,
+        Level::DEBUG => 3,
+        Level::TRACE => 4,
+    }
+}
+
+/// The shared state behind `EventCounters`.
+#[derive(Debug, Default)]
+struct Counts {
+    /// The number of events per level, in the order of `LEVELS`.
+    levels: [AtomicU64; 5],
+
+    /// The target prefixes that are counted, along with the number of events per prefix.
+    targets: Box<[(String, AtomicU64)]>,
+}
+
+/// Live counts of the events seen by a `CountingLayer`.
+///
+/// Instances are obtained from `CountingLayer::counters` and are cheap to clone, so that they can
+/// be moved into the state of a status line callback. The counts are stored in atomics, so reading
+/// them never blocks the threads that emit events.
+///
+/// The counters also implement `Widget`, which renders the number of errors and warnings, e.g.
+/// `3 errors, 12 warnings`.
+#[derive(Clone, Debug, Default)]
+pub struct EventCounters(Arc<Counts>);
+
+impl EventCounters {
+    /// Return the number of events of the provided level.
+    pub fn get(&self, level: Level) -> u64 {
+        self.0.levels[level_index(&level)].load(Ordering::Relaxed)
+    }
+
+    /// Return the number of error events.
+    pub fn errors(&self) -> u64 {
+        self.get(Level::ERROR)
+    }
+
+    /// Return the number of warning events.
+    pub fn warnings(&self) -> u64 {
+        self.get(Level::WARN)
+    }
+
+    /// Return the total number of events of all levels.
+    pub fn total(&self) -> u64 {
+        LEVELS.iter()
+            .map(|level| self.get(*level))
+            .sum()
+    }
+
+    /// Return the number of events whose target matches the provided prefix, or `None` when the
+    /// prefix was not passed to `CountingLayer::with_targets`.
+    pub fn target(&self, prefix: &str) -> Option<u64> {
+        self.0.targets
+            .iter()
+            .find(|(target, _)| target == prefix)
+            .map(|(_, count)| count.load(Ordering::Relaxed))
+    }
+
+    /// Return the counted target prefixes along with their counts, in the order in which they were
+    /// configured.
+    pub fn targets(&self) -> Vec<(&str, u64)> {
+        self.0.targets
+            .iter()
+            .map(|(target, count)| (target.as_str(), count.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// Count an event of the provided level and target.
+    fn count(&self, level: &Level, target: &str) {
+        self.0.levels[level_index(level)].fetch_add(1, Ordering::Relaxed);
+
+        for (prefix, count) in self.0.targets.iter() {
+            if utils::matches_target(target, prefix) {
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Formats the number of errors and warnings, e.g. `1 error, 12 warnings`.
+impl Display for EventCounters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |count: u64| if count == 1 { "" } else { "s" };
+        let errors = self.errors();
+        let warnings = self.warnings();
+
+        write!(f, "{errors} error{}, {warnings} warning{}", plural(errors), plural(warnings))
+    }
+}
+
+impl Widget for EventCounters {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        write!(output, "{self}")
+    }
+}
+
+impl_make_callback!(EventCounters);
+
+/// A `tracing_subscriber` layer that counts events by level and by target.
+///
+/// Counting events in a separate layer means that they do not need to be formatted or written to
+/// be counted, and that the counts do not depend on how the formatting layer maps events to
+/// writes. This layer counts every event it sees and shares the counts through an `EventCounters`
+/// instance that can be read from a status line callback, e.g. to show `3 errors, 12 warnings`.
+///
+/// Counting only touches atomics, so the layer adds very little overhead to busy programs. The
+/// layer should be added to a subscriber alongside the formatting layer that writes to the status
+/// line handler. Events that are disabled by a filter on another layer are still counted, unless
+/// the filter is applied to this layer as well.
+#[derive(Debug, Default)]
+pub struct CountingLayer {
+    /// The shared counts.
+    counters: EventCounters,
+}
+
+impl CountingLayer {
+    /// Create a new layer that counts events by level.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new layer that counts events by level, and additionally counts the events whose
+    /// target matches each of the provided prefixes.
+    ///
+    /// A prefix matches a target when it is equal to the target, or when the target is a module
+    /// below it. For example, the prefix `hyper` matches `hyper` and `hyper::client`, but not
+    /// `hyper_util`. An event is counted for every prefix that it matches.
+    pub fn with_targets<I>(targets: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let targets = targets.into_iter()
+            .map(|target| (target.into(), AtomicU64::new(0)))
+            .collect();
+
+        Self {
+            counters: EventCounters(Arc::new(Counts {
+                levels: Default::default(),
+                targets,
+            })),
+        }
+    }
+
+    /// Return the shared counts that are updated by this layer.
+    pub fn counters(&self) -> EventCounters {
+        self.counters.clone()
+    }
+}
+
+impl<S: Subscriber> Layer<S> for CountingLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+
+        self.counters.count(metadata.level(), metadata.target());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::{error, info, warn};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[test]
+    fn events_are_counted_by_level_and_target() {
+        let layer = CountingLayer::with_targets(["app::db", "hyper"]);
+        let counters = layer.counters();
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            error!(target: "app::db::pool", "connection lost");
+            warn!(target: "hyper", "slow response");
+            warn!(target: "hyper_util", "retrying");
+            info!(target: "app", "started");
+        });
+
+        assert_eq!(counters.errors(), 1);
+        assert_eq!(counters.warnings(), 2);
+        assert_eq!(counters.total(), 4);
+        assert_eq!(counters.targets(), [("app::db", 1), ("hyper", 1)]);
+        assert_eq!(counters.target("app"), None);
+        assert_eq!(counters.to_string(), "1 error, 2 warnings");
+    }
+}
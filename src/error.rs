@@ -0,0 +1,133 @@
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The policy that determines what happens when writing to the output or invoking the status line
+/// callback fails.
+///
+/// Errors can not be reported to the code that emitted the log message, because `tracing` does not
+/// propagate errors from writers. The policy decides how the log handler recovers instead. The
+/// number of errors and the most recent error are tracked in the handler's `Health`.
+#[derive(Clone, Default)]
+pub enum ErrorPolicy {
+    /// Ignore the error and carry on as if nothing happened.
+    Ignore,
+
+    /// Stop drawing the status line, but keep writing log messages. This is useful when a failing
+    /// status line callback should not take down the logs.
+    #[default]
+    DisableStatus,
+
+    /// Retry failed writes to the output, waiting for `delay` between attempts. When all attempts
+    /// fail, or when the status line callback fails, the error is ignored.
+    Retry {
+        /// The number of times a failed write is retried.
+        attempts: u32,
+
+        /// The time to wait before retrying.
+        delay: Duration,
+    },
+
+    /// Hand the error to the provided closure and carry on.
+    Custom(Arc<dyn Fn(&io::Error) + Send + Sync>),
+}
+
+impl ErrorPolicy {
+    /// Create a policy that hands errors to the provided closure.
+    pub fn custom<F>(handler: F) -> Self
+    where
+        F: Fn(&io::Error) + Send + Sync + 'static,
+    {
+        Self::Custom(Arc::new(handler))
+    }
+}
+
+impl fmt::Debug for ErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ignore => f.write_str("Ignore"),
+            Self::DisableStatus => f.write_str("DisableStatus"),
+
+            Self::Retry { attempts, delay } => f.debug_struct("Retry")
+                .field("attempts", attempts)
+                .field("delay", delay)
+                .finish(),
+
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// The health of a log handler, as reported by `ThreadedHandler::health` and
+/// `UnthreadedHandler::health`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Health {
+    /// The number of errors that occurred so far.
+    errors: u64,
+
+    /// The kind of the most recent error.
+    last_error: Option<io::ErrorKind>,
+
+    /// True when the status line was disabled because of an error.
+    status_disabled: bool,
+}
+
+impl Health {
+    /// Return true when no errors occurred.
+    pub fn is_healthy(&self) -> bool {
+        self.errors == 0
+    }
+
+    /// Return the number of errors that occurred so far. Errors that were resolved by retrying
+    /// are not counted.
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// Return the kind of the most recent error.
+    pub fn last_error(&self) -> Option<io::ErrorKind> {
+        self.last_error
+    }
+
+    /// Return true when the status line was disabled by `ErrorPolicy::DisableStatus`.
+    pub fn status_disabled(&self) -> bool {
+        self.status_disabled
+    }
+}
+
+/// The shared state behind `Health`, updated by the renderer and read by the log handlers.
+#[derive(Debug, Default)]
+pub(crate) struct HealthState {
+    /// The number of errors that occurred so far.
+    errors: AtomicU64,
+
+    /// The kind of the most recent error.
+    last_error: Mutex<Option<io::ErrorKind>>,
+
+    /// True when the status line was disabled because of an error.
+    status_disabled: AtomicBool,
+}
+
+impl HealthState {
+    /// Record an error.
+    pub fn record(&self, error: &io::Error) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        *self.last_error.lock().expect("Health mutex was poisoned") = Some(error.kind());
+    }
+
+    /// Record that the status line was disabled.
+    pub fn disable_status(&self) {
+        self.status_disabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Return a snapshot of the current health.
+    pub fn snapshot(&self) -> Health {
+        Health {
+            errors: self.errors.load(Ordering::Relaxed),
+            last_error: *self.last_error.lock().expect("Health mutex was poisoned"),
+            status_disabled: self.status_disabled.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Human-readable formatting of byte sizes, durations, and rates.
+//!
+//! The types in this module are thin wrappers that implement `Display`, so they can be used
+//! directly in `write!` calls inside status line callbacks, e.g.
+//! `write!(output, "{} at {}", HumanBytes(received), HumanByteRate(rate))`.
+
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+/// The binary unit prefixes used for byte sizes.
+const BYTE_UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+/// The decimal unit prefixes used for counts.
+const COUNT_UNITS: [&str; 7] = ["", "k", "M", "G", "T", "P", "E"];
+
+/// Scale a value down by the provided base until it is below the base, returning the scaled
+/// value and the index of the unit it is expressed in.
+///
+/// The value is compared after rounding it to the single decimal that is written, so that e.g.
+/// 1023.96 KiB is shown as `1.0 MiB` rather than `1024.0 KiB`.
+fn scale(mut value: f64, base: f64, units: usize) -> (f64, usize) {
+    let mut unit = 0;
+
+    while (value * 10.0).round() / 10.0 >= base && unit < units - 1 {
+        value /= base;
+        unit += 1;
+    }
+
+    (value, unit)
+}
+
+/// Write a value using the provided units, with one decimal for anything but the base unit.
+fn write_scaled(f: &mut fmt::Formatter<'_>, value: f64, base: f64, units: &[&str], separator: &str) -> fmt::Result {
+    match scale(value, base, units.len()) {
+        (value, 0) if value.fract() == 0.0 => write!(f, "{value:.0}{separator}{}", units[0]),
+        (value, 0) => write!(f, "{value:.1}{separator}{}", units[0]),
+        (value, unit) => write!(f, "{value:.1}{separator}{}", units[unit]),
+    }
+}
+
+/// A byte size, formatted using binary units, e.g. `512 B` or `1.5 MiB`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HumanBytes(pub u64);
+
+impl Display for HumanBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_scaled(f, self.0 as f64, 1024.0, &BYTE_UNITS, " ")
+    }
+}
+
+/// A duration, formatted using its two most significant units, e.g. `42s`, `3m 05s`, or `1h 02m`.
+///
+/// Durations of less than a second are rounded to whole seconds, and durations of a day or more
+/// are shown in days and hours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HumanDuration(pub Duration);
+
+impl Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.0.as_secs_f64().round() as u64;
+        let (days, hours, minutes, seconds) = (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60, seconds % 60);
+
+        if days > 0 {
+            write!(f, "{days}d {hours:02}h")
+        } else if hours > 0 {
+            write!(f, "{hours}h {minutes:02}m")
+        } else if minutes > 0 {
+            write!(f, "{minutes}m {seconds:02}s")
+        } else {
+            write!(f, "{seconds}s")
+        }
+    }
+}
+
+/// A rate in items per second, formatted using decimal units, e.g. `12.5/s` or `3.2k/s`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HumanRate(pub f64);
+
+impl Display for HumanRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_scaled(f, self.0.max(0.0), 1000.0, &COUNT_UNITS, "")?;
+        write!(f, "/s")
+    }
+}
+
+/// A rate in bytes per second, formatted using binary units, e.g. `1.5 MiB/s`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HumanByteRate(pub f64);
+
+impl Display for HumanByteRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_scaled(f, self.0.max(0.0), 1024.0, &BYTE_UNITS, " ")?;
+        write!(f, "/s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_formatted_with_units() {
+        assert_eq!(HumanBytes(512).to_string(), "512 B");
+        assert_eq!(HumanBytes(1536).to_string(), "1.5 KiB");
+        assert_eq!(HumanBytes(5 * 1024 * 1024 * 1024).to_string(), "5.0 GiB");
+
+        assert_eq!(HumanDuration(Duration::from_millis(400)).to_string(), "0s");
+        assert_eq!(HumanDuration(Duration::from_secs(42)).to_string(), "42s");
+        assert_eq!(HumanDuration(Duration::from_secs(185)).to_string(), "3m 05s");
+        assert_eq!(HumanDuration(Duration::from_secs(3720)).to_string(), "1h 02m");
+        assert_eq!(HumanDuration(Duration::from_secs(90000)).to_string(), "1d 01h");
+
+        assert_eq!(HumanRate(12.0).to_string(), "12/s");
+        assert_eq!(HumanRate(0.4).to_string(), "0.4/s");
+        assert_eq!(HumanRate(3200.0).to_string(), "3.2k/s");
+        assert_eq!(HumanByteRate(1.5 * 1024.0 * 1024.0).to_string(), "1.5 MiB/s");
+    }
+
+    #[test]
+    fn values_that_round_up_to_the_base_use_the_next_unit() {
+        assert_eq!(HumanBytes(1023).to_string(), "1023 B");
+        assert_eq!(HumanBytes(1024).to_string(), "1.0 KiB");
+        assert_eq!(HumanBytes(1_048_575).to_string(), "1.0 MiB");
+
+        assert_eq!(HumanRate(999.94).to_string(), "999.9/s");
+        assert_eq!(HumanRate(999.96).to_string(), "1.0k/s");
+        assert_eq!(HumanRate(999_960.0).to_string(), "1.0M/s");
+
+        assert_eq!(HumanByteRate(1023.96 * 1024.0).to_string(), "1.0 MiB/s");
+        assert_eq!(HumanByteRate(1023.94 * 1024.0).to_string(), "1023.9 KiB/s");
+    }
+}
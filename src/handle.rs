@@ -0,0 +1,292 @@
+use std::io::{self, Write};
+use std::iter;
+use std::panic;
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event};
+use crossterm::terminal;
+
+use crate::{LogSender, StatusWriter};
+use crate::pager::{self, PagerCommand};
+use crate::renderer::{Renderer, Toast};
+use crate::unthreaded::Shared;
+
+/// The maximum time a panic hook waits for the log handler to write the panic message.
+const PANIC_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The operations that a `StatusHandle` can perform on the state of an unthreaded handler.
+///
+/// This trait exists so that the handle does not need to be generic over the status line callback
+/// and writer types of the handler it belongs to.
+pub(crate) trait Control: Send + Sync {
+    /// Redraw the status line without writing a log entry.
+    fn redraw(&self) -> io::Result<()>;
+
+    /// Erase the status area and write the provided panic message, giving up after `timeout`.
+    fn panic(&self, message: &[u8], timeout: Duration);
+
+    /// Handle a command for the history pager.
+    fn pager(&self, command: PagerCommand) -> io::Result<()>;
+
+    /// Show a notification below the status lines and redraw the status line, starting the
+    /// ticker that removes the notification once it expires.
+    fn notify(self: Arc<Self>, toast: Toast) -> io::Result<()>;
+}
+
+/// Lock the state of an unthreaded handler.
+///
+/// A poisoned mutex means that a log writer or the status line callback paniced. The status area
+/// is not drawn anymore in that case, so an error is returned instead of panicking in the caller.
+fn lock<T, W>(state: &Mutex<Renderer<T, W>>) -> io::Result<MutexGuard<'_, Renderer<T, W>>>
+where
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
+    W: Write,
+{
+    state.lock().map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "The log handler has paniced"))
+}
+
+impl<T, W> Control for Shared<T, W>
+where
+    T: FnMut(&mut StatusWriter) -> io::Result<()> + Send + 'static,
+    W: Write + Send + 'static,
+{
+    fn redraw(&self) -> io::Result<()> {
+        lock(&self.renderer)?.redraw();
+
+        Ok(())
+    }
+
+    fn panic(&self, message: &[u8], timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+
+        // The panicking thread may hold the lock itself, e.g. when the status line callback
+        // paniced, so the lock is only tried until the deadline passes
+        loop {
+            match self.renderer.try_lock() {
+                Ok(mut renderer) => return renderer.panic(message),
+                Err(TryLockError::Poisoned(poisoned)) => return poisoned.into_inner().panic(message),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => thread::sleep(Duration::from_millis(1)),
+                Err(TryLockError::WouldBlock) => return,
+            }
+        }
+    }
+
+    fn pager(&self, command: PagerCommand) -> io::Result<()> {
+        lock(&self.renderer)?.pager(command);
+
+        Ok(())
+    }
+
+    fn notify(self: Arc<Self>, toast: Toast) -> io::Result<()> {
+        let mut renderer = lock(&self.renderer)?;
+
+        renderer.notify(toast);
+        renderer.redraw();
+        drop(renderer);
+
+        // Wake up the ticker thread, so that it redraws the status line when the toast expires
+        self.wake.notify_all();
+        self.start_ticker(None);
+
+        Ok(())
+    }
+}
+
+/// The handler-specific part of a `StatusHandle`.
+#[derive(Clone)]
+enum Inner {
+    /// A handle to a threaded handler, which communicates through the log channel.
+    Threaded(LogSender),
+
+    /// A handle to an unthreaded handler, which operates directly on the shared writer state.
+    Unthreaded(Arc<dyn Control>),
+}
+
+/// A handle to the status line of a log handler.
+///
+/// Handles are obtained by calling `handle` on a `ThreadedHandler` or `UnthreadedHandler`. They
+/// are cheap to clone and can be sent to other threads, so that the status line can be controlled
+/// from anywhere in the program, even after the handler itself was moved into a subscriber.
+#[derive(Clone)]
+pub struct StatusHandle {
+    inner: Inner,
+}
+
+impl StatusHandle {
+    /// Create a handle for a threaded handler.
+    pub(crate) fn threaded(log_sender: LogSender) -> Self {
+        Self {
+            inner: Inner::Threaded(log_sender),
+        }
+    }
+
+    /// Create a handle for an unthreaded handler.
+    pub(crate) fn unthreaded(state: Arc<dyn Control>) -> Self {
+        Self {
+            inner: Inner::Unthreaded(state),
+        }
+    }
+
+    /// Redraw the status line without writing a log message.
+    ///
+    /// This is useful when the status line shows shared state that was updated by the program,
+    /// because the status line is normally only redrawn after writing log messages.
+    ///
+    /// For threaded handlers the redraw happens asynchronously on the background thread. For
+    /// unthreaded handlers the status line is redrawn before this function returns.
+    ///
+    /// Returns an error if the handler has stopped, or if it paniced, e.g. in the status line
+    /// callback.
+    pub fn redraw(&self) -> io::Result<()> {
+        match &self.inner {
+            Inner::Threaded(log_sender) => log_sender.redraw(),
+            Inner::Unthreaded(state) => state.redraw(),
+        }
+    }
+
+    /// Briefly show a message in an extra row below the status lines.
+    ///
+    /// The message is removed once the provided duration has passed. It is never written to the
+    /// scrollback, which makes notifications suitable for short confirmations such as "config
+    /// reloaded" that should not clutter the log. Newlines in the message are replaced with spaces,
+    /// and long messages are truncated to the width of the terminal. Multiple notifications are
+    /// shown in separate rows, oldest first.
+    ///
+    /// The status line is redrawn when the notification expires, even when no log messages arrive.
+    /// For unthreaded handlers the first notification starts a small background thread for this,
+    /// unless the handler runs one already. Nothing is shown when the output is not a terminal.
+    pub fn notify(&self, message: &str, duration: Duration) -> io::Result<()> {
+        let toast = Toast::new(message, duration);
+
+        match &self.inner {
+            Inner::Threaded(log_sender) => log_sender.notify(toast),
+
+            Inner::Unthreaded(state) => state.clone().notify(toast),
+        }
+    }
+
+    /// Send a command to the history pager.
+    fn pager_command(&self, command: PagerCommand) -> io::Result<()> {
+        match &self.inner {
+            Inner::Threaded(log_sender) => log_sender.pager(command),
+
+            Inner::Unthreaded(state) => state.pager(command),
+        }
+    }
+
+    /// Show the log history in a full-screen pager, reading keys from the terminal until the pager
+    /// is closed.
+    ///
+    /// The pager shows the log messages kept by `Builder::history` on the alternate screen, so
+    /// that the live view is left untouched. It can be scrolled using the arrow keys, page up and
+    /// page down, home and end, or `j`, `k`, `space`, `b`, `g`, and `G`. Typing `/` starts a
+    /// search, which is confirmed using enter, after which `n` and `N` jump to the next and
+    /// previous match. Matches are highlighted. Searches ignore case unless the query contains
+    /// uppercase characters. Pressing `q` or escape closes the pager.
+    ///
+    /// Log messages that are written while the pager is shown are added to the pager, and written
+    /// above the status area once the pager is closed. The status area is drawn again afterwards.
+    ///
+    /// Terminal raw mode is enabled while reading keys, unless it was enabled already. This
+    /// function blocks until the pager is closed, so it should not be called from an async task.
+    /// Use `pager_with` when the program has an event loop of its own.
+    pub fn pager(&self) -> io::Result<()> {
+        let raw_mode = terminal::is_raw_mode_enabled()?;
+
+        if !raw_mode {
+            terminal::enable_raw_mode()?;
+        }
+
+        let result = self.read_pager_keys(iter::repeat_with(event::read));
+
+        // Raw mode is disabled before the pager is closed, so that the log messages held back by
+        // the pager are written in cooked mode. The pager is closed even when that fails, so that
+        // log messages are not held back forever.
+        let restored = match raw_mode {
+            true => Ok(()),
+            false => terminal::disable_raw_mode(),
+        };
+
+        let closed = self.pager_command(PagerCommand::Close);
+
+        result.and(restored).and(closed)
+    }
+
+    /// Show the log history in a full-screen pager, taking the key presses from the provided
+    /// terminal events until the pager is closed.
+    ///
+    /// This works like `pager`, except that raw mode is not enabled, and events are not read from
+    /// the terminal. Resize events redraw the pager, and other events are ignored. The pager is
+    /// closed when `q` or escape is pressed, or when the events run out.
+    pub fn pager_with<I>(&self, events: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = io::Result<Event>>,
+    {
+        let result = self.read_pager_keys(events);
+
+        self.pager_command(PagerCommand::Close)?;
+
+        result
+    }
+
+    /// Open the pager and forward key presses to it until one of them closes the pager.
+    fn read_pager_keys<I>(&self, events: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = io::Result<Event>>,
+    {
+        self.pager_command(PagerCommand::Open)?;
+
+        let mut searching = false;
+
+        for event in events {
+            match event? {
+                Event::Key(key) if pager::closes(&key, &mut searching) => break,
+                Event::Key(key) => self.pager_command(PagerCommand::Key(key))?,
+                Event::Resize(..) => self.pager_command(PagerCommand::Redraw)?,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Install a panic hook that restores the terminal before the panic is reported.
+    ///
+    /// When the program panics the hook disables raw mode, erases the status area, shows the
+    /// cursor, and writes the panic message as a plain log line. It then calls the previously
+    /// installed panic hook, which normally prints the panic message to standard error. The status
+    /// line is not drawn anymore after a panic, but log messages are still written, and raw mode is
+    /// not enabled again for them.
+    ///
+    /// The hook waits at most half a second for the log handler, so that a panic in the status line
+    /// callback or on the log writing thread can not hang the program.
+    pub fn install_panic_hook(&self) {
+        let handle = self.clone();
+        let previous = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            let thread = thread::current();
+            let name = thread.name().unwrap_or("<unnamed>");
+            let message = format!("thread '{name}' {info}\n").into_bytes();
+
+            // Disable raw mode first, so that the panic message is written in cooked mode. Raw mode
+            // may not be enabled, errors are irrelevant at this point.
+            let _ = terminal::disable_raw_mode();
+
+            match &handle.inner {
+                Inner::Threaded(log_sender) => {
+                    log_sender.panic(message, PANIC_TIMEOUT);
+                }
+
+                Inner::Unthreaded(state) => state.panic(&message, PANIC_TIMEOUT),
+            }
+
+            // A log entry that was being written while the hook ran may have enabled raw mode again
+            let _ = terminal::disable_raw_mode();
+
+            previous(info);
+        }));
+    }
+}
@@ -0,0 +1,307 @@
+//! A width-aware status line layout.
+//!
+//! A `Layout` arranges widgets in three groups: aligned to the left, centered, and aligned to the
+//! right. The line is padded to the width of the terminal, so that it never wraps. When the
+//! terminal is too narrow the segments with the lowest priority are dropped, and if the remaining
+//! segments still do not fit the line is truncated with an ellipsis.
+
+use std::io::{self, Write};
+
+use crate::{StatusWriter, ansi};
+use crate::widgets::{Widget, impl_make_callback};
+
+/// A widget with a priority, for use in a `Layout`.
+///
+/// When the terminal is too narrow to show all segments, segments with a lower priority are
+/// dropped before segments with a higher priority. The default priority is 0.
+pub struct Segment {
+    /// The widget that renders the segment's content.
+    widget: Box<dyn Widget>,
+
+    /// The priority of the segment.
+    priority: i32,
+}
+
+impl Segment {
+    /// Create a new segment with the default priority.
+    pub fn new(widget: impl Widget + 'static) -> Self {
+        Self {
+            widget: Box::new(widget),
+            priority: 0,
+        }
+    }
+
+    /// Set the priority of the segment.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
+impl<T: Widget + 'static> From<T> for Segment {
+    fn from(widget: T) -> Self {
+        Self::new(widget)
+    }
+}
+
+/// The alignment of a group of segments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// A rendered segment.
+struct Rendered {
+    /// The alignment group the segment belongs to.
+    alignment: Alignment,
+
+    /// The priority of the segment.
+    priority: i32,
+
+    /// The rendered text, which may contain escape sequences.
+    text: String,
+
+    /// The number of columns the text occupies.
+    width: usize,
+}
+
+/// A status line with left-aligned, centered, and right-aligned segments.
+///
+/// The layout is a widget itself, so it can be used directly as a status line callback or be
+/// stacked with other lines using `widgets::Lines`.
+pub struct Layout {
+    /// The segments, in the order in which they were added.
+    segments: Vec<(Alignment, Segment)>,
+
+    /// The separator written between segments of the same group.
+    separator: String,
+
+    /// The text that marks a truncated line.
+    ellipsis: String,
+
+    /// A fixed line width. The terminal width is used when this is `None`.
+    width: Option<u16>,
+}
+
+impl Layout {
+    /// Create a new empty layout.
+    pub fn new() -> Self {
+        Self {
+            segments: Vec::new(),
+            separator: " ".to_owned(),
+            ellipsis: "…".to_owned(),
+            width: None,
+        }
+    }
+
+    /// Append a segment to the left-aligned group.
+    pub fn left(mut self, segment: impl Into<Segment>) -> Self {
+        self.segments.push((Alignment::Left, segment.into()));
+        self
+    }
+
+    /// Append a segment to the centered group.
+    pub fn center(mut self, segment: impl Into<Segment>) -> Self {
+        self.segments.push((Alignment::Center, segment.into()));
+        self
+    }
+
+    /// Append a segment to the right-aligned group.
+    pub fn right(mut self, segment: impl Into<Segment>) -> Self {
+        self.segments.push((Alignment::Right, segment.into()));
+        self
+    }
+
+    /// Use the provided separator between segments of the same group. The default is a single
+    /// space.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Use the provided text to mark truncated lines. The default is `…`.
+    pub fn ellipsis(mut self, ellipsis: impl Into<String>) -> Self {
+        self.ellipsis = ellipsis.into();
+        self
+    }
+
+    /// Use a fixed line width instead of the width of the terminal.
+    pub fn width(mut self, width: u16) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Render all segments of the layout.
+    fn render_segments(&mut self, output: &StatusWriter) -> io::Result<Vec<Rendered>> {
+        self.segments
+            .iter_mut()
+            .map(|(alignment, segment)| {
+                let mut buffer = output.nested();
+                segment.widget.render(&mut buffer)?;
+
+                let text = String::from_utf8_lossy(buffer.as_bytes()).into_owned();
+
+                Ok(Rendered {
+                    alignment: *alignment,
+                    priority: segment.priority,
+                    width: ansi::width(&text),
+                    text,
+                })
+            })
+            .collect()
+    }
+
+    /// Join the segments of a group, returning the text and its width.
+    fn group(&self, segments: &[Rendered], alignment: Alignment) -> (String, usize) {
+        let mut text = String::new();
+        let mut width = 0;
+
+        for segment in segments.iter().filter(|segment| segment.alignment == alignment) {
+            if width > 0 {
+                text.push_str(&self.separator);
+                width += ansi::width(&self.separator);
+            }
+
+            text.push_str(&segment.text);
+            width += segment.width;
+        }
+
+        (text, width)
+    }
+
+    /// Return the minimum width needed to show the provided segments, i.e. the widths of all groups
+    /// with a single space between non-empty groups.
+    fn required_width(&self, segments: &[Rendered]) -> usize {
+        let widths = [Alignment::Left, Alignment::Center, Alignment::Right]
+            .map(|alignment| self.group(segments, alignment).1);
+
+        let gaps = widths.iter().filter(|width| **width > 0).count().saturating_sub(1);
+
+        widths.iter().sum::<usize>() + gaps
+    }
+
+    /// Lay out the segments for the provided line width.
+    fn arrange(&self, mut segments: Vec<Rendered>, width: usize) -> String {
+        // Drop the segments with the lowest priority until the rest fits. Of multiple segments with
+        // the same priority the last one is dropped first.
+        while segments.len() > 1 && self.required_width(&segments) > width {
+            let lowest = segments
+                .iter()
+                .enumerate()
+                .min_by_key(|(index, segment)| (segment.priority, usize::MAX - index))
+                .map(|(index, _)| index)
+                .expect("There is at least one segment");
+
+            segments.remove(lowest);
+        }
+
+        let (left, left_width) = self.group(&segments, Alignment::Left);
+        let (center, center_width) = self.group(&segments, Alignment::Center);
+        let (right, right_width) = self.group(&segments, Alignment::Right);
+
+        // Truncate the line when even the remaining segments do not fit
+        if self.required_width(&segments) > width {
+            let line = [left, center, right]
+                .into_iter()
+                .filter(|group| !group.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            return ansi::truncate(&line, width, &self.ellipsis);
+        }
+
+        // Center the middle group on the line, shifting it if it would overlap the other groups
+        let gap = |width: usize| usize::from(width > 0);
+        let right_start = width - right_width;
+        let center_start = ((width - center_width) / 2)
+            .max(left_width + gap(left_width))
+            .min(right_start.saturating_sub(center_width + gap(right_width)));
+
+        let mut line = left;
+
+        if center_width > 0 {
+            line.push_str(&" ".repeat(center_start - left_width));
+            line.push_str(&center);
+        }
+
+        let used = if center_width > 0 { center_start + center_width } else { left_width };
+
+        line.push_str(&" ".repeat(right_start - used));
+        line.push_str(&right);
+
+        line
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget for Layout {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        let width = self.width.unwrap_or(output.width());
+        let segments = self.render_segments(output)?;
+        let line = self.arrange(segments, width as usize);
+
+        write!(output, "{line}")
+    }
+}
+
+impl_make_callback!(Layout);
+
+#[cfg(test)]
+mod tests {
+    use crate::widgets::Text;
+
+    use super::*;
+
+    /// Render a layout for the provided line width.
+    fn render(layout: &mut Layout, width: u16) -> String {
+        let mut output = StatusWriter::new(80, 24);
+        layout.width = Some(width);
+        layout.render(&mut output).unwrap();
+
+        String::from_utf8(output.as_bytes().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn segments_are_dropped_by_priority() {
+        let mut layout = Layout::new()
+            .left(Segment::new(Text::new("aaaa")).priority(2))
+            .left(Text::new("bbbb"))
+            .right(Text::new("cccc"))
+            .right(Segment::new(Text::new("dd")).priority(1));
+
+        assert_eq!(render(&mut layout, 17), "aaaa bbbb cccc dd");
+
+        // Of the segments with the lowest priority the last one is dropped first
+        assert_eq!(render(&mut layout, 16), "aaaa bbbb     dd");
+        assert_eq!(render(&mut layout, 11), "aaaa     dd");
+        assert_eq!(render(&mut layout, 6), "aaaa  ");
+
+        // The last segment is truncated instead of dropped
+        assert_eq!(render(&mut layout, 3), "aa…");
+        assert_eq!(render(&mut layout, 0), "");
+    }
+
+    #[test]
+    fn center_group_is_centered_and_shifted() {
+        let mut layout = Layout::new()
+            .left(Text::new("left"))
+            .center(Text::new("mid"))
+            .right(Text::new("r"));
+
+        assert_eq!(render(&mut layout, 21), "left     mid        r");
+
+        // The center group moves right rather than overlapping the left group
+        let mut layout = Layout::new()
+            .left(Text::new("a long left"))
+            .center(Text::new("mid"));
+
+        assert_eq!(render(&mut layout, 16), "a long left mid ");
+    }
+}
@@ -1,11 +1,40 @@
+mod ansi;
 mod builder;
+mod counters;
+mod error;
+mod handle;
 mod log_bridge;
+mod pager;
+mod progress;
+mod rate;
+mod renderer;
+mod resize;
+mod spans;
+mod status_writer;
 mod threaded;
+#[cfg(feature = "tokio")]
+mod tokio_handler;
 mod unthreaded;
 mod utils;
 
+pub mod format;
+pub mod layout;
+pub mod testing;
+pub mod widgets;
+
 pub use builder::{Builder, MakeCallback};
-pub use threaded::ThreadedHandler;
+pub use counters::{CountingLayer, EventCounters};
+pub use error::{ErrorPolicy, Health};
+pub use handle::StatusHandle;
+pub use log_bridge::{DropCounter, OverflowPolicy};
+pub use progress::{ProgressLayer, ProgressSpan, ProgressSpans};
+pub use rate::RateEstimator;
+pub use renderer::{FinishMode, RenderMode, SummaryFn, SynchronizedOutput};
+pub use spans::{ActiveSpan, ActiveSpans, SpanStack, StatusLayer};
+pub use status_writer::StatusWriter;
+pub use threaded::{ThreadedHandler, WorkerGuard};
+#[cfg(feature = "tokio")]
+pub use tokio_handler::{TokioGuard, TokioHandler};
 pub use unthreaded::UnthreadedHandler;
 
 use log_bridge::{LogReceiver, LogSender};
@@ -1,29 +1,354 @@
+use std::collections::VecDeque;
 use std::io::{self, Write};
 use std::ops::Deref;
-use std::sync::{Arc, Mutex};
-use std::sync::mpsc::{self, Receiver, SendError, SyncSender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::time::{Duration, Instant};
+
+use tracing::{Level, Metadata};
+use tracing::callsite::Identifier;
+
+use crate::pager::PagerCommand;
+use crate::renderer::{Entry, Toast};
+
+/// The policy that determines what happens when a log entry is written while the channel between
+/// the logging threads and the log writing thread is full.
+///
+/// Log entries of error events are never discarded, so that errors are not lost under heavy load.
+/// They are queued beyond the capacity of the channel instead. This requires the entries to be
+/// written through `MakeWriter::make_writer_for`, which `tracing_subscriber`'s formatting layer
+/// uses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Block the logging thread until the log writing thread has made room in the channel. No log
+    /// entries are lost, but a slow terminal will slow down the program. This can deadlock Tokio
+    /// handlers, see `Builder::tokio`.
+    #[default]
+    Block,
+
+    /// Discard the log entry that is being written.
+    DropNewest,
+
+    /// Discard the oldest log entry in the channel to make room for the new one.
+    DropOldest,
+
+    /// Never block and never discard anything. The channel grows without bounds, and the channel
+    /// capacity is ignored.
+    Unbounded,
+}
+
+/// A counter for the number of log entries that were discarded because of the overflow policy.
+///
+/// The counter is cheap to clone and all clones share the same value. It can be read from a status
+/// line callback, e.g. to show a warning when log entries are being lost.
+#[derive(Clone, Debug, Default)]
+pub struct DropCounter(Arc<AtomicU64>);
+
+impl DropCounter {
+    /// Create a new counter, starting at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the total number of log entries that were dropped so far.
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Increment the counter by one.
+    fn increment(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A pooled log entry buffer, along with the level, target, and callsite of the event that
+/// produced it.
+#[derive(Clone, Default)]
+struct Record {
+    /// The formatted log entry.
+    buffer: Vec<u8>,
+
+    /// The level of the event, or `None` when the entry was not written for a specific event.
+    level: Option<Level>,
+
+    /// The target of the event. This is empty when the level is `None`.
+    target: String,
+
+    /// The callsite of the event, or `None` when the entry was not written for a specific event.
+    callsite: Option<Identifier>,
+}
+
+impl Record {
+    /// Return true when the entry must never be discarded by the overflow policy.
+    fn is_protected(&self) -> bool {
+        self.level == Some(Level::ERROR)
+    }
+}
+
+/// A message sent from a `LogSender` to a `LogReceiver`.
+enum Message {
+    /// A log entry.
+    Entry(Record),
+
+    /// A request to redraw the status line without writing a log entry.
+    Redraw,
+
+    /// A notification that the program is panicking.
+    Panic(PanicMessage),
+
+    /// A command for the history pager.
+    Pager(PagerCommand),
+
+    /// A notification to show below the status lines.
+    Notify(Toast),
+
+    /// A request to stop processing. An explicit closing message is used so that log senders do
+    /// not need to have their lifetimes managed and no blocking synchronization is required.
+    Close,
+}
+
+/// The mutable part of a `Queue`.
+struct QueueState {
+    /// The queued messages, oldest first.
+    messages: VecDeque<Message>,
+
+    /// The number of `Entry` messages in the queue.
+    entries: usize,
+
+    /// True when the channel was closed. Messages can no longer be sent when this is set.
+    closed: bool,
+}
+
+/// A message queue with a configurable capacity and overflow policy.
+///
+/// A custom queue is used instead of an mpsc channel, because the channels in the standard library
+/// do not allow a sender to discard queued messages.
+struct Queue {
+    /// The queue state.
+    state: Mutex<QueueState>,
+
+    /// A condition variable that is signalled when a message was pushed.
+    pushed: Condvar,
+
+    /// A condition variable that is signalled when messages were popped.
+    popped: Condvar,
+
+    /// A notification that wakes up an async receiver when a message was pushed.
+    #[cfg(feature = "tokio")]
+    notify: tokio::sync::Notify,
+
+    /// The maximum number of log entries in the queue.
+    capacity: usize,
+
+    /// What to do when the queue is full.
+    policy: OverflowPolicy,
+
+    /// The number of log entries that were discarded.
+    dropped: DropCounter,
+}
+
+impl Queue {
+    /// Lock the queue state.
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().expect("Log queue mutex was poisoned")
+    }
+
+    /// Wake up the receiver after a message was pushed.
+    fn wake(&self) {
+        self.pushed.notify_one();
+
+        #[cfg(feature = "tokio")]
+        self.notify.notify_one();
+    }
+
+    /// Push a log entry into the queue, applying the overflow policy. The entry is handed back if
+    /// the queue was closed.
+    ///
+    /// Error entries are never discarded. They are queued beyond the capacity of the queue instead
+    /// when the policy would discard them.
+    fn push_entry(&self, record: Record) -> Result<(), Record> {
+        let mut state = self.lock();
+
+        while !state.closed && state.entries >= self.capacity {
+            match self.policy {
+                OverflowPolicy::Block => {
+                    state = self.popped.wait(state).expect("Log queue mutex was poisoned");
+                }
+
+                _ if record.is_protected() => break,
+
+                OverflowPolicy::DropNewest => {
+                    self.dropped.increment();
+                    return Ok(());
+                }
+
+                OverflowPolicy::DropOldest => {
+                    let oldest = state.messages
+                        .iter()
+                        .position(|message| matches!(message, Message::Entry(record) if !record.is_protected()));
+
+                    // Discard the new entry instead when the queue is full of error entries
+                    let Some(oldest) = oldest else {
+                        self.dropped.increment();
+                        return Ok(());
+                    };
+
+                    state.messages.remove(oldest);
+                    state.entries -= 1;
+                    self.dropped.increment();
+                }
+
+                OverflowPolicy::Unbounded => break,
+            }
+        }
+
+        if state.closed {
+            return Err(record);
+        }
+
+        state.messages.push_back(Message::Entry(record));
+        state.entries += 1;
+        self.wake();
+
+        Ok(())
+    }
+
+    /// Push a message that is not a log entry into the queue. These messages are never subject to
+    /// the overflow policy. Returns false if the queue was closed.
+    fn push_control(&self, message: Message) -> bool {
+        let mut state = self.lock();
+
+        if state.closed {
+            return false;
+        }
+
+        // A single pending redraw is sufficient
+        if matches!(message, Message::Redraw) && state.messages.iter().any(|message| matches!(message, Message::Redraw)) {
+            return true;
+        }
+
+        state.closed = matches!(message, Message::Close);
+        state.messages.push_back(message);
+        self.wake();
+
+        true
+    }
+
+    /// Take the oldest message from the locked queue state, if any.
+    fn take(&self, state: &mut QueueState) -> Option<Message> {
+        let message = state.messages.pop_front()?;
+
+        if matches!(message, Message::Entry(_)) {
+            state.entries -= 1;
+            self.popped.notify_all();
+        }
+
+        Some(message)
+    }
+
+    /// Pop the oldest message from the queue, waiting until `deadline` if necessary. Waits forever
+    /// if no deadline is given. Returns `None` when the deadline expires.
+    fn pop(&self, deadline: Option<Instant>) -> Option<Message> {
+        let mut state = self.lock();
+
+        loop {
+            if let Some(message) = self.take(&mut state) {
+                return Some(message);
+            }
+
+            state = match deadline {
+                Some(deadline) => {
+                    let timeout = deadline.checked_duration_since(Instant::now())?;
+
+                    self.pushed
+                        .wait_timeout(state, timeout)
+                        .expect("Log queue mutex was poisoned")
+                        .0
+                }
+
+                None => self.pushed.wait(state).expect("Log queue mutex was poisoned"),
+            };
+        }
+    }
+
+    /// Pop the oldest message from the queue without waiting.
+    fn try_pop(&self) -> Option<Message> {
+        let mut state = self.lock();
+
+        self.take(&mut state)
+    }
+
+    /// Close the queue, waking up any senders that are blocked on a full queue.
+    fn close(&self) {
+        self.lock().closed = true;
+        self.popped.notify_all();
+    }
+}
 
 /// A log entry sender. This is used to send log entries to a consumer on a background thread.
-/// Propagation of entries is done by means of an mpsc channel. The sender and receiver share a
+/// Propagation of entries is done by means of a message queue. The sender and receiver share a
 /// second channel to propagate back consumed buffers. This reduces the number of allocations made
 /// by the log sender.
 ///
 /// The `LogSender` implements `Write` (hence the need for a pool of buffers instead of taking
 /// ownership of entries) and thus can be passed to `tracing_subscriber` as the return type of a
 /// `MakeWriter` impl.
+///
+/// A sender created by `for_event` tags the entries written to it with the level and target of an
+/// event. The metadata is kept in a pooled record as well, so that tagging entries does not
+/// allocate once the pool is warmed up.
 #[derive(Clone)]
 pub struct LogSender {
-    /// A sender that propagates log message buffers to a LogReceiver instance. Sending an empty
-    /// message indicates that the receiver should stop processing. An explicit closing message is
-    /// used here so that log senders do not need to have their lifetimes managed and no blocking
-    /// synchronization is required.
-    sender: SyncSender<Option<Vec<u8>>>,
+    /// A queue that propagates log message buffers and other messages to a LogReceiver instance.
+    queue: Arc<Queue>,
 
     /// A free list of log message buffers.
-    pool: Arc<Mutex<Receiver<Vec<u8>>>>,
+    pool: Arc<Mutex<Receiver<Record>>>,
+
+    /// A sender used to return the event record to the pool when the sender is dropped.
+    recycle: SyncSender<Record>,
+
+    /// A record holding the level and target of the event that is being written, or `None` when
+    /// entries are not written for a specific event.
+    event: Option<Record>,
 }
 
 impl LogSender {
+    /// Take an empty record from the pool, or allocate a new one if the pool is empty.
+    fn record(&self) -> Record {
+        let pool = self.pool.lock().expect("Pool mutex was poisoned");
+
+        match pool.try_recv() {
+            Ok(mut record) => {
+                record.buffer.clear();
+                record.level = None;
+                record.target.clear();
+                record.callsite = None;
+                record
+            }
+
+            // An empty or closed pool should allocate a new record
+            Err(_) => Record::default(),
+        }
+    }
+
+    /// Return a sender that tags the entries written to it with the level, target, and callsite of
+    /// the provided event metadata.
+    pub fn for_event(&self, metadata: &Metadata<'_>) -> Self {
+        let mut event = self.record();
+        event.level = Some(*metadata.level());
+        event.target.push_str(metadata.target());
+        event.callsite = Some(metadata.callsite());
+
+        Self {
+            queue: self.queue.clone(),
+            pool: self.pool.clone(),
+            recycle: self.recycle.clone(),
+            event: Some(event),
+        }
+    }
+
     /// Close the log sender. No further entries can be sent through the channel after this.
     ///
     /// To prevent any missing log messages the `Write` impl will print messages directly to
@@ -32,33 +357,77 @@ impl LogSender {
     /// sender is replaced with another log consumer. The stdout fallback merely exists as a
     /// debugging aid.
     pub fn close(&mut self) {
-        let _ = self.sender.send(None);
+        self.queue.push_control(Message::Close);
+    }
+
+    /// Ask the receiver to erase the status area and write the provided panic message, waiting at
+    /// most `timeout` for it to finish.
+    ///
+    /// Returns false if the receiver has stopped, or did not finish in time.
+    pub fn panic(&self, message: Vec<u8>, timeout: Duration) -> bool {
+        let (ack, acknowledged) = mpsc::sync_channel(1);
+
+        self.queue.push_control(Message::Panic(PanicMessage {
+            message,
+            ack,
+        })) && acknowledged.recv_timeout(timeout).is_ok()
+    }
+
+    /// Ask the receiver to redraw the status line without writing a log entry.
+    ///
+    /// Returns an error if the receiver has stopped.
+    pub fn redraw(&self) -> io::Result<()> {
+        if self.queue.push_control(Message::Redraw) {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "The log writing thread has stopped"))
+        }
+    }
+
+    /// Send a command to the history pager of the receiver.
+    ///
+    /// Returns an error if the receiver has stopped.
+    pub(crate) fn pager(&self, command: PagerCommand) -> io::Result<()> {
+        if self.queue.push_control(Message::Pager(command)) {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "The log writing thread has stopped"))
+        }
+    }
+
+    /// Ask the receiver to show a notification below the status lines.
+    ///
+    /// Returns an error if the receiver has stopped.
+    pub(crate) fn notify(&self, toast: Toast) -> io::Result<()> {
+        if self.queue.push_control(Message::Notify(toast)) {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "The log writing thread has stopped"))
+        }
+    }
+
+    /// Return the counter that tracks the number of dropped log entries.
+    pub fn drop_counter(&self) -> DropCounter {
+        self.queue.dropped.clone()
     }
 }
 
 impl Write for LogSender {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let pool = self.pool.lock().expect("Pool mutex was poisoned");
-
-        let buffer = match pool.try_recv() {
-            Ok(mut buffer) => {
-                buffer.truncate(0);
-                buffer.extend(buf);
-                buffer
-            },
-
-            // An empty or closed pool should allocate a new buffer
-            Err(_) => buf.to_owned(),
-        };
+        let mut record = self.record();
+        record.buffer.extend_from_slice(buf);
 
-        // Release the lock, critical section ends here
-        drop(pool);
+        if let Some(event) = &self.event {
+            record.level = event.level;
+            record.target.clone_from(&event.target);
+            record.callsite.clone_from(&event.callsite);
+        }
 
-        match self.sender.send(Some(buffer)) {
+        match self.queue.push_entry(record) {
             Ok(()) => (),
 
             // Directly print logs if the reader is closed
-            Err(SendError(buffer)) => print!("{}", std::str::from_utf8(&buffer.unwrap()).unwrap_or("")),
+            Err(record) => print!("{}", std::str::from_utf8(&record.buffer).unwrap_or("")),
         }
 
         Ok(buf.len())
@@ -69,6 +438,16 @@ impl Write for LogSender {
     }
 }
 
+/// A `Drop` impl that returns the event record to the pool.
+impl Drop for LogSender {
+    fn drop(&mut self) {
+        // Records are simply deallocated when the pool is full
+        if let Some(event) = self.event.take() {
+            let _ = self.recycle.try_send(event);
+        }
+    }
+}
+
 /// An enumeration that lists the things that can go wrong when trying to receive data from a
 /// LogRecever.
 pub enum TryRecvError {
@@ -79,30 +458,39 @@ pub enum TryRecvError {
     Closed,
 }
 
-impl From<mpsc::TryRecvError> for TryRecvError {
-    fn from(value: mpsc::TryRecvError) -> Self {
-        match value {
-            mpsc::TryRecvError::Empty => Self::Empty,
-            mpsc::TryRecvError::Disconnected => Self::Closed,
-        }
-    }
+/// An enumeration that lists the things that can go wrong when waiting for data from a
+/// LogReceiver with a timeout.
+pub enum RecvTimeoutError {
+    /// No new log entries arrived before the timeout expired.
+    Timeout,
+
+    /// The channel is closed.
+    Closed,
 }
 
-/// A log entry. This contains a buffer and a sender to propagate the buffer back into the buffer
+/// A log entry. This contains a record and a sender to propagate the record back into the buffer
 /// pool.
 pub struct LogEntry {
-    /// The buffer containing the log entry. This is normally always `Some`, until the Drop impl is
-    /// called, which takes the entry and sends it back into the buffer pool.
-    buffer: Option<Vec<u8>>,
+    /// The record containing the log entry. This is normally always `Some`, until the Drop impl is
+    /// called, which takes the record and sends it back into the buffer pool.
+    record: Option<Record>,
 
-    /// A sender to a pool of unused buffers.
-    pool: SyncSender<Vec<u8>>,
+    /// A sender to a pool of unused records.
+    pool: SyncSender<Record>,
+}
+
+impl LogEntry {
+    /// Return the record of the entry.
+    fn record(&self) -> &Record {
+        self.record.as_ref().unwrap()
+    }
 }
 
 impl Drop for LogEntry {
     fn drop(&mut self) {
-        if let Some(buffer) = self.buffer.take() {
-            let _ = self.pool.send(buffer);
+        // Records are simply deallocated when the pool is full
+        if let Some(record) = self.record.take() {
+            let _ = self.pool.try_send(record);
         }
     }
 }
@@ -111,65 +499,297 @@ impl Deref for LogEntry {
     type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
-        self.buffer.as_ref().unwrap()
+        &self.record().buffer
     }
 }
 
+impl AsRef<[u8]> for LogEntry {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Entry for LogEntry {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn level(&self) -> Option<Level> {
+        self.record().level
+    }
+
+    fn target(&self) -> Option<&str> {
+        self.record().level.map(|_| self.record().target.as_str())
+    }
+
+    fn callsite(&self) -> Option<&Identifier> {
+        self.record().callsite.as_ref()
+    }
+}
+
+/// A panic message sent by a panic hook.
+pub struct PanicMessage {
+    /// The formatted panic message, ending with a newline.
+    pub message: Vec<u8>,
+
+    /// A sender used to tell the panic hook that the message was written.
+    ack: SyncSender<()>,
+}
+
+impl PanicMessage {
+    /// Tell the panic hook that the message was written.
+    pub fn acknowledge(self) {
+        let _ = self.ack.try_send(());
+    }
+}
+
+/// An event received by a `LogReceiver`.
+pub enum Event {
+    /// A log entry that should be written.
+    Entry(LogEntry),
+
+    /// A request to redraw the status line.
+    Redraw,
+
+    /// A notification that the program is panicking. The status area should be erased and the
+    /// panic message should be written.
+    Panic(PanicMessage),
+
+    /// A command for the history pager.
+    Pager(PagerCommand),
+
+    /// A notification that should be shown below the status lines.
+    Notify(Toast),
+}
+
 /// A receiver for log entries.
 pub struct LogReceiver {
-    /// The channel used to propagate buffers.
-    receiver: Receiver<Option<Vec<u8>>>,
+    /// The queue used to propagate buffers.
+    queue: Arc<Queue>,
+
+    /// A sender used to return used records to a pool for reuse.
+    pool: SyncSender<Record>,
 
-    /// A sender used to return used buffers to a pool for reuse.
-    pool: SyncSender<Vec<u8>>,
+    /// The value of the drop counter when dropped entries were last reported.
+    reported: u64,
 }
 
 impl LogReceiver {
-    /// Wait for the next log entry to arrive, wrapping it in a `LogEntry` struct. Returns `None`
-    /// when the last `LogSender` was dropped.
-    pub fn recv(&mut self) -> Option<LogEntry> {
-        self.receiver
-            .recv()
-            .ok()
-            .flatten()
-            .map(|buffer| LogEntry {
-                buffer: Some(buffer),
-                pool: self.pool.clone(),
-            })
-    }
-
-    /// Try to receive a next log entry without blocking, wrapping it in a `LogEntry`. Returns
-    /// either the received entry or a `TryRecvError` indicating why a log entry could not be
+    /// Wrap a record into a log entry.
+    fn entry(&self, record: Record) -> LogEntry {
+        LogEntry {
+            record: Some(record),
+            pool: self.pool.clone(),
+        }
+    }
+
+    /// Wrap a received message into an event. Returns `None` for closing messages.
+    fn event(&self, message: Message) -> Option<Event> {
+        match message {
+            Message::Entry(record) => Some(Event::Entry(self.entry(record))),
+            Message::Redraw => Some(Event::Redraw),
+            Message::Panic(panic) => Some(Event::Panic(panic)),
+            Message::Pager(command) => Some(Event::Pager(command)),
+            Message::Notify(toast) => Some(Event::Notify(toast)),
+            Message::Close => None,
+        }
+    }
+
+    /// Wait for the next event to arrive, wrapping log entries in a `LogEntry` struct. Returns
+    /// `None` when the channel was closed.
+    pub fn recv(&mut self) -> Option<Event> {
+        self.queue
+            .pop(None)
+            .and_then(|message| self.event(message))
+    }
+
+    /// Wait asynchronously for the next event to arrive, wrapping log entries in a `LogEntry`
+    /// struct. Returns `None` when the channel was closed.
+    #[cfg(feature = "tokio")]
+    pub async fn recv_async(&mut self) -> Option<Event> {
+        loop {
+            if let Some(message) = self.queue.try_pop() {
+                return self.event(message);
+            }
+
+            // A notification that arrives before this point is stored as a permit, so no wake-ups
+            // are lost
+            self.queue.notify.notified().await;
+        }
+    }
+
+    /// Wait for the next event to arrive for at most `timeout`, wrapping log entries in a
+    /// `LogEntry`. Returns either the received event or a `RecvTimeoutError` indicating why an
+    /// event could not be retrieved.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Event, RecvTimeoutError> {
+        let message = self.queue
+            .pop(Some(Instant::now() + timeout))
+            .ok_or(RecvTimeoutError::Timeout)?;
+
+        self.event(message).ok_or(RecvTimeoutError::Closed)
+    }
+
+    /// Try to receive a next event without blocking, wrapping log entries in a `LogEntry`.
+    /// Returns either the received event or a `TryRecvError` indicating why an event could not be
     /// retrieved.
-    pub fn try_recv(&mut self) -> Result<LogEntry, TryRecvError> {
-        self.receiver
-            .try_recv()
-            .map_err(TryRecvError::from)?
-            .ok_or(TryRecvError::Closed)
-            .map(|buffer| LogEntry {
-                buffer: Some(buffer),
-                pool: self.pool.clone(),
-            })
-    }
-}
-
-/// Initialize a new log sender/receiver pair.
-pub fn init() -> (LogSender, LogReceiver) {
-    // TODO: Determine proper default backpressure
-    // TODO: Make backpressure optional
-    // TODO: Make backpressure customizable
-    let (sender, receiver) = mpsc::sync_channel(1024);
-    let (pool_sender, pool_receiver) = mpsc::sync_channel(1024);
+    pub fn try_recv(&mut self) -> Result<Event, TryRecvError> {
+        let message = self.queue
+            .try_pop()
+            .ok_or(TryRecvError::Empty)?;
+
+        self.event(message).ok_or(TryRecvError::Closed)
+    }
+
+    /// Return a marker entry that reports the number of log entries that were dropped since the
+    /// previous marker, or `None` if no entries were dropped.
+    pub fn dropped_marker(&mut self) -> Option<LogEntry> {
+        let dropped = self.queue.dropped.get();
+        let count = dropped - self.reported;
+
+        if count == 0 {
+            return None;
+        }
+
+        self.reported = dropped;
+
+        Some(self.entry(Record {
+            buffer: format!("[{count} log lines dropped]\n").into_bytes(),
+            ..Record::default()
+        }))
+    }
+}
+
+/// A `Drop` impl that closes the queue, so that senders stop waiting for the receiver.
+impl Drop for LogReceiver {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
+
+/// Initialize a new log sender/receiver pair with the given capacity, overflow policy, and drop
+/// counter.
+pub fn init(capacity: usize, policy: OverflowPolicy, dropped: DropCounter) -> (LogSender, LogReceiver) {
+    let queue = Arc::new(Queue {
+        state: Mutex::new(QueueState {
+            messages: VecDeque::new(),
+            entries: 0,
+            closed: false,
+        }),
+        pushed: Condvar::new(),
+        popped: Condvar::new(),
+        #[cfg(feature = "tokio")]
+        notify: tokio::sync::Notify::new(),
+        capacity: match policy {
+            OverflowPolicy::Unbounded => usize::MAX,
+            _ => capacity.max(1),
+        },
+        policy,
+        dropped,
+    });
+
+    let (pool_sender, pool_receiver) = mpsc::sync_channel(capacity.clamp(1, 1024));
 
     (
         LogSender {
-            sender,
+            queue: queue.clone(),
             pool: Arc::new(Mutex::new(pool_receiver)),
+            recycle: pool_sender.clone(),
+            event: None,
         },
 
         LogReceiver {
-            receiver,
+            reported: queue.dropped.get(),
+            queue,
             pool: pool_sender,
         },
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use tracing::{error, info};
+    use tracing_subscriber::fmt::MakeWriter;
+
+    use super::*;
+
+    /// A `MakeWriter` impl that tags entries with their event metadata.
+    struct Tagging(LogSender);
+
+    impl<'a> MakeWriter<'a> for Tagging {
+        type Writer = LogSender;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.0.clone()
+        }
+
+        fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+            self.0.for_event(meta)
+        }
+    }
+
+    /// Receive the queued entries as level, target, and message text.
+    fn drain(receiver: &mut LogReceiver) -> Vec<(Option<Level>, Option<String>, String)> {
+        let mut entries = Vec::new();
+
+        while let Ok(Event::Entry(entry)) = receiver.try_recv() {
+            let text = String::from_utf8_lossy(&entry).trim_end().to_owned();
+            entries.push((entry.level(), entry.target().map(str::to_owned), text));
+        }
+
+        entries
+    }
+
+    #[test]
+    fn entries_carry_the_level_and_target_of_their_event() {
+        let (sender, mut receiver) = init(16, OverflowPolicy::Block, DropCounter::new());
+
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(Tagging(sender.clone()))
+            .with_ansi(false)
+            .without_time()
+            .with_target(false)
+            .with_level(false)
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            error!(target: "app::db", "lost");
+        });
+
+        let mut plain = sender.clone();
+        plain.write_all(b"plain\n").unwrap();
+
+        assert_eq!(drain(&mut receiver), [
+            (Some(Level::ERROR), Some("app::db".to_owned()), "lost".to_owned()),
+            (None, None, "plain".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn error_entries_are_never_dropped() {
+        for policy in [OverflowPolicy::DropNewest, OverflowPolicy::DropOldest] {
+            let (sender, mut receiver) = init(1, policy, DropCounter::new());
+
+            let subscriber = tracing_subscriber::fmt()
+                .with_writer(Tagging(sender.clone()))
+                .with_ansi(false)
+                .without_time()
+                .with_target(false)
+                .with_level(false)
+                .finish();
+
+            tracing::subscriber::with_default(subscriber, || {
+                error!("first error");
+                info!("dropped");
+                error!("second error");
+            });
+
+            let messages: Vec<String> = drain(&mut receiver)
+                .into_iter()
+                .map(|(_, _, text)| text)
+                .collect();
+
+            assert_eq!(messages, ["first error", "second error"], "{policy:?}");
+            assert_eq!(sender.drop_counter().get(), 1);
+        }
+    }
+}
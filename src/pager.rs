@@ -0,0 +1,360 @@
+use std::io;
+
+use crossterm::cursor::{Hide, MoveTo};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{Clear, ClearType};
+
+use crate::ansi;
+
+/// A command that controls the history pager of a log handler.
+#[derive(Debug)]
+pub(crate) enum PagerCommand {
+    /// Erase the status area and show the log history on the alternate screen.
+    Open,
+
+    /// Handle a key press.
+    Key(KeyEvent),
+
+    /// Draw the pager again, e.g. after the terminal was resized.
+    Redraw,
+
+    /// Leave the alternate screen, write the log entries that arrived while the pager was open, and
+    /// draw the status area again.
+    Close,
+}
+
+/// Return true when the provided key closes the pager.
+///
+/// The pager itself is driven by the log handler, which may live on another thread, so the code
+/// that reads key events decides when to stop. Escape and `q` close the pager, except while a
+/// search query is typed, which is tracked using `searching`. Ctrl-C always closes the pager.
+pub(crate) fn closes(key: &KeyEvent, searching: &mut bool) -> bool {
+    if key.kind == KeyEventKind::Release {
+        return false;
+    }
+
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        return true;
+    }
+
+    match key.code {
+        KeyCode::Enter | KeyCode::Esc if *searching => *searching = false,
+        _ if *searching => (),
+        KeyCode::Char('/') => *searching = true,
+        KeyCode::Char('q') | KeyCode::Esc => return true,
+        _ => (),
+    }
+
+    false
+}
+
+/// Find the first occurrence of a query in a line, returning its byte range.
+///
+/// The search ignores case, unless the query contains uppercase characters.
+fn find(line: &str, query: &str) -> Option<(usize, usize)> {
+    let ignore_case = !query.chars().any(char::is_uppercase);
+
+    let equal = |a: char, b: char| if ignore_case {
+        a.to_lowercase().eq(b.to_lowercase())
+    } else {
+        a == b
+    };
+
+    line.char_indices().find_map(|(start, _)| {
+        let mut rest = line[start..].char_indices();
+        let mut end = start;
+
+        for expected in query.chars() {
+            let (offset, c) = rest.next()?;
+
+            if !equal(c, expected) {
+                return None;
+            }
+
+            end = start + offset + c.len_utf8();
+        }
+
+        Some((start, end))
+    })
+}
+
+/// The state of the full-screen history pager.
+///
+/// The pager shows the lines of the log history in all rows but the last, which shows the position
+/// in the history, the search query being typed, or a message.
+pub(crate) struct Pager {
+    /// The lines of the log history, without escape sequences.
+    lines: Vec<String>,
+
+    /// The index of the line shown in the top row.
+    top: usize,
+
+    /// True when the view stays scrolled to the bottom as lines are added.
+    follow: bool,
+
+    /// The query that is highlighted, and that is searched for using `n` and `N`.
+    query: Option<String>,
+
+    /// The search query that is being typed, if any.
+    input: Option<String>,
+
+    /// A message that is shown in the bottom row until the next key press.
+    message: Option<&'static str>,
+}
+
+impl Pager {
+    /// Create a new pager that shows the provided log entries, scrolled to the bottom.
+    pub fn new<'a>(entries: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut pager = Self {
+            lines: Vec::new(),
+            top: 0,
+            follow: true,
+            query: None,
+            input: None,
+            message: None,
+        };
+
+        for entry in entries {
+            pager.push(entry);
+        }
+
+        pager
+    }
+
+    /// Add a log entry to the end of the history.
+    pub fn push(&mut self, entry: &str) {
+        self.lines.extend(entry.lines().map(str::to_owned));
+    }
+
+    /// Return the number of lines shown on a screen with the provided number of rows.
+    fn page(rows: u16) -> usize {
+        usize::from(rows.saturating_sub(1)).max(1)
+    }
+
+    /// Return the index of the top line when the view is scrolled to the bottom.
+    fn bottom(&self, rows: u16) -> usize {
+        self.lines.len().saturating_sub(Self::page(rows))
+    }
+
+    /// Scroll the view so that the provided line is at the top, following new lines when the view
+    /// ends up at the bottom.
+    fn scroll_to(&mut self, top: usize, rows: u16) {
+        let bottom = self.bottom(rows);
+
+        self.top = top.min(bottom);
+        self.follow = self.top == bottom;
+    }
+
+    /// Scroll to the next line that matches the query, starting at the provided line and searching
+    /// forward or backward.
+    fn search(&mut self, from: usize, forward: bool, rows: u16) {
+        let Some(query) = self.query.as_deref().filter(|query| !query.is_empty()) else {
+            return;
+        };
+
+        let matches = |index: &usize| find(&self.lines[*index], query).is_some();
+
+        let found = if forward {
+            (from..self.lines.len()).find(matches)
+        } else {
+            (0..from.min(self.lines.len())).rev().find(matches)
+        };
+
+        match found {
+            Some(index) => self.scroll_to(index, rows),
+            None => self.message = Some("Pattern not found"),
+        }
+    }
+
+    /// Handle a key press on a screen with the provided number of rows.
+    pub fn key(&mut self, key: KeyEvent, rows: u16) {
+        if key.kind == KeyEventKind::Release {
+            return;
+        }
+
+        self.message = None;
+
+        let top = if self.follow { self.bottom(rows) } else { self.top };
+
+        let page = Self::page(rows);
+
+        if let Some(input) = &mut self.input {
+            match key.code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => drop(input.pop()),
+                KeyCode::Esc => self.input = None,
+
+                KeyCode::Enter => {
+                    self.query = self.input.take();
+                    self.search(top, true, rows);
+                }
+
+                _ => (),
+            }
+
+            return;
+        }
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.scroll_to(top.saturating_sub(1), rows),
+            KeyCode::Down | KeyCode::Enter | KeyCode::Char('j') => self.scroll_to(top + 1, rows),
+            KeyCode::PageUp | KeyCode::Char('b') => self.scroll_to(top.saturating_sub(page), rows),
+            KeyCode::PageDown | KeyCode::Char(' ' | 'f') => self.scroll_to(top + page, rows),
+            KeyCode::Home | KeyCode::Char('g') => self.scroll_to(0, rows),
+            KeyCode::End | KeyCode::Char('G') => self.scroll_to(usize::MAX, rows),
+            KeyCode::Char('/') => self.input = Some(String::new()),
+            KeyCode::Char('n') => self.search(top + 1, true, rows),
+            KeyCode::Char('N') => self.search(top, false, rows),
+            _ => (),
+        }
+    }
+
+    /// Add the commands that draw the pager on a screen of the provided size to the frame.
+    pub fn render(&mut self, frame: &mut Vec<u8>, (columns, rows): (u16, u16)) -> io::Result<()> {
+        let columns = usize::from(columns);
+
+        if self.follow {
+            self.top = self.bottom(rows);
+        }
+
+        self.top = self.top.min(self.bottom(rows));
+
+        crossterm::queue!(
+            frame,
+            Hide,
+            MoveTo(0, 0),
+            Clear(ClearType::All),
+        )?;
+
+        let shown = self.lines.iter().skip(self.top).take(Self::page(rows));
+
+        for (row, line) in (0..).zip(shown) {
+            crossterm::queue!(
+                frame,
+                MoveTo(0, row),
+            )?;
+
+            self.render_line(frame, &ansi::truncate(line, columns, ""))?;
+        }
+
+        let footer = match (&self.input, self.message) {
+            (Some(input), _) => format!("/{input}"),
+            (None, Some(message)) => message.to_owned(),
+            (None, None) if self.lines.is_empty() => "No log history (q: quit)".to_owned(),
+
+            (None, None) => {
+                let last = (self.top + Self::page(rows)).min(self.lines.len());
+
+                format!(
+                    "Lines {}-{last} of {} (q: quit, /: search, n/N: next/previous match)",
+                    self.top + 1,
+                    self.lines.len(),
+                )
+            }
+        };
+
+        crossterm::queue!(
+            frame,
+            MoveTo(0, rows.saturating_sub(1)),
+            SetAttribute(Attribute::Reverse),
+            Print(ansi::truncate(&footer, columns, "…")),
+            SetAttribute(Attribute::Reset),
+        )
+    }
+
+    /// Add a line to the frame, highlighting the occurrences of the query.
+    fn render_line(&self, frame: &mut Vec<u8>, mut line: &str) -> io::Result<()> {
+        let query = self.query.as_deref().unwrap_or_default();
+
+        while let Some((start, end)) = find(line, query).filter(|_| !query.is_empty()) {
+            crossterm::queue!(
+                frame,
+                Print(&line[..start]),
+                SetAttribute(Attribute::Reverse),
+                Print(&line[start..end]),
+                SetAttribute(Attribute::NoReverse),
+            )?;
+
+            line = &line[end..];
+        }
+
+        crossterm::queue!(
+            frame,
+            Print(line),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use crossterm::style::Attribute;
+
+    use super::*;
+    use crate::testing::VirtualTerminal;
+
+    /// Draw the pager on a virtual terminal of the provided size.
+    fn draw(pager: &mut Pager, columns: u16, rows: u16) -> VirtualTerminal {
+        let mut terminal = VirtualTerminal::new(columns, rows);
+        let mut frame = Vec::new();
+
+        pager.render(&mut frame, (columns, rows)).unwrap();
+        terminal.write_all(&frame).unwrap();
+
+        terminal
+    }
+
+    /// Create a key press event for a character.
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::from(KeyCode::Char(c))
+    }
+
+    #[test]
+    fn pager_scrolls_and_searches() {
+        let entries: Vec<String> = (1..=10).map(|index| format!("entry {index}")).collect();
+        let mut pager = Pager::new(entries.iter().map(String::as_str));
+
+        // The view starts at the bottom
+        let terminal = draw(&mut pager, 60, 4);
+        assert_eq!(terminal.screen()[..3], ["entry 8", "entry 9", "entry 10"]);
+        assert!(terminal.row(3).starts_with("Lines 8-10 of 10"));
+
+        pager.key(key('g'), 4);
+        pager.key(key('j'), 4);
+        assert_eq!(draw(&mut pager, 60, 4).screen()[..3], ["entry 2", "entry 3", "entry 4"]);
+
+        // Search for a query, which is highlighted
+        for c in "/ENTRY 5".chars().map(key).chain([KeyEvent::from(KeyCode::Enter)]) {
+            pager.key(c, 4);
+        }
+
+        assert_eq!(draw(&mut pager, 60, 4).row(3), "Pattern not found");
+
+        for c in "/entry 5".chars().map(key).chain([KeyEvent::from(KeyCode::Enter)]) {
+            pager.key(c, 4);
+        }
+
+        let terminal = draw(&mut pager, 60, 4);
+        assert_eq!(terminal.screen()[..3], ["entry 5", "entry 6", "entry 7"]);
+        assert!(terminal.cell(0, 0).style().attributes.has(Attribute::Reverse));
+        assert!(!terminal.cell(0, 1).style().attributes.has(Attribute::Reverse));
+
+        // New entries are shown when the view is at the bottom
+        pager.key(key('G'), 4);
+        pager.push("entry 11\nentry 12");
+        assert_eq!(draw(&mut pager, 60, 4).screen()[..3], ["entry 10", "entry 11", "entry 12"]);
+    }
+
+    #[test]
+    fn escape_only_closes_outside_of_searches() {
+        let mut searching = false;
+        let escape = KeyEvent::from(KeyCode::Esc);
+
+        assert!(!closes(&key('/'), &mut searching));
+        assert!(!closes(&key('q'), &mut searching));
+        assert!(!closes(&escape, &mut searching));
+        assert!(closes(&escape, &mut searching));
+    }
+}
@@ -0,0 +1,503 @@
+use std::fmt::Debug;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use tracing::{Subscriber, span};
+use tracing::field::{Field, Visit};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::{StatusHandle, StatusWriter};
+use crate::widgets::{self, Widget, impl_make_callback};
+
+/// The span field that holds the message of a progress line.
+const MESSAGE_FIELD: &str = "status.msg";
+
+/// The span field that holds the current position of a progress line.
+const POSITION_FIELD: &str = "status.pos";
+
+/// The span field that holds the position at which a progress line is complete.
+const LENGTH_FIELD: &str = "status.len";
+
+/// The progress fields recorded for a span. This is stored in the span's extensions.
+#[derive(Clone, Debug, Default)]
+struct ProgressFields {
+    /// The recorded message.
+    message: Option<String>,
+
+    /// The recorded position.
+    position: Option<u64>,
+
+    /// The recorded length.
+    length: Option<u64>,
+}
+
+impl ProgressFields {
+    /// Record a numeric field value.
+    fn set_number(&mut self, field: &Field, value: u64) {
+        match field.name() {
+            POSITION_FIELD => self.position = Some(value),
+            LENGTH_FIELD => self.length = Some(value),
+            MESSAGE_FIELD => self.message = Some(value.to_string()),
+            _ => (),
+        }
+    }
+}
+
+impl Visit for ProgressFields {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set_number(field, value);
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set_number(field, value.max(0) as u64);
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == MESSAGE_FIELD {
+            self.message = Some(value.to_owned());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        let value = format!("{value:?}");
+
+        match value.parse() {
+            Ok(number) => self.set_number(field, number),
+            Err(_) if field.name() == MESSAGE_FIELD => self.message = Some(value),
+            Err(_) => (),
+        }
+    }
+}
+
+/// The progress of a span that is shown as a line in the status area.
+#[derive(Clone, Debug)]
+pub struct ProgressSpan {
+    /// The span's ID, used to apply recorded field values.
+    id: span::Id,
+
+    /// The name of the span.
+    name: &'static str,
+
+    /// The target of the span.
+    target: &'static str,
+
+    /// The recorded progress fields.
+    fields: ProgressFields,
+
+    /// The moment at which the span was first entered.
+    started: Instant,
+}
+
+impl ProgressSpan {
+    /// Return the name of the span.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Return the target of the span.
+    pub fn target(&self) -> &'static str {
+        self.target
+    }
+
+    /// Return the value of the `status.msg` field, if it was recorded.
+    pub fn message(&self) -> Option<&str> {
+        self.fields.message.as_deref()
+    }
+
+    /// Return the value of the `status.pos` field, if it was recorded.
+    pub fn position(&self) -> Option<u64> {
+        self.fields.position
+    }
+
+    /// Return the value of the `status.len` field, if it was recorded.
+    pub fn length(&self) -> Option<u64> {
+        self.fields.length
+    }
+
+    /// Return the completed fraction between 0 and 1, or `None` when the position or length was
+    /// not recorded.
+    pub fn fraction(&self) -> Option<f64> {
+        match (self.position()?, self.length()?) {
+            (_, 0) => Some(0.0),
+            (position, length) => Some((position as f64 / length as f64).clamp(0.0, 1.0)),
+        }
+    }
+
+    /// Return the time elapsed since the span was first entered.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+/// A snapshot source for the progress of the spans that are currently shown, as well as a widget
+/// that renders them.
+///
+/// Instances are obtained from `ProgressLayer::spans` and are cheap to clone, so that they can be
+/// moved into the state of a status line callback. All clones share the same spans.
+///
+/// As a widget, every span is rendered on its own line, e.g. `compiling foo [=====>    ] 12/20`.
+/// The line shows the message of the span, or its name when no message was recorded, followed by
+/// a progress bar when both a position and a length were recorded. The widget renders nothing
+/// when no spans are shown, so that it takes up no lines in a `Lines` widget.
+#[derive(Clone)]
+pub struct ProgressSpans {
+    /// The shown spans, in the order in which they were first entered.
+    spans: Arc<Mutex<Vec<ProgressSpan>>>,
+
+    /// The width of the progress bars in characters, excluding the brackets.
+    width: usize,
+}
+
+impl ProgressSpans {
+    /// Lock the shared state.
+    fn lock(&self) -> MutexGuard<'_, Vec<ProgressSpan>> {
+        self.spans.lock().expect("Progress spans mutex was poisoned")
+    }
+
+    /// Set the width of the progress bars in characters, excluding the brackets. The bars are 20
+    /// characters wide by default.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Return the spans that are currently shown, in the order in which they were first entered.
+    pub fn snapshot(&self) -> Vec<ProgressSpan> {
+        self.lock().clone()
+    }
+
+    /// Show a span, unless it is already shown. Returns true if the span was added.
+    fn show(&self, span: ProgressSpan) -> bool {
+        let mut spans = self.lock();
+
+        if spans.iter().any(|shown| shown.id == span.id) {
+            return false;
+        }
+
+        spans.push(span);
+        true
+    }
+
+    /// Update the fields of a shown span. Returns true if the span is shown.
+    fn update(&self, id: &span::Id, fields: &ProgressFields) -> bool {
+        let mut spans = self.lock();
+
+        match spans.iter_mut().find(|span| span.id == *id) {
+            Some(span) => {
+                span.fields.clone_from(fields);
+                true
+            }
+
+            None => false,
+        }
+    }
+
+    /// Stop showing a span. Returns true if the span was shown.
+    fn hide(&self, id: &span::Id) -> bool {
+        let mut spans = self.lock();
+        let count = spans.len();
+
+        spans.retain(|span| span.id != *id);
+        spans.len() != count
+    }
+}
+
+impl Default for ProgressSpans {
+    fn default() -> Self {
+        Self {
+            spans: Arc::default(),
+            width: 20,
+        }
+    }
+}
+
+impl Widget for ProgressSpans {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        for (index, span) in self.lock().iter().enumerate() {
+            if index > 0 {
+                writeln!(output)?;
+            }
+
+            write!(output, "{}", span.message().unwrap_or(span.name))?;
+
+            match (span.fraction(), span.position(), span.length()) {
+                (Some(fraction), Some(position), Some(length)) => {
+                    write!(output, " {} {position}/{length}", widgets::bar(fraction, self.width))?;
+                }
+
+                (_, Some(position), _) => write!(output, " {position}")?,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl_make_callback!(ProgressSpans);
+
+/// A `tracing_subscriber` layer that shows spans with progress fields as lines in the status area,
+/// similar to a multi-progress bar.
+///
+/// Any span that declares one of the fields `status.msg`, `status.pos`, or `status.len` gets its own
+/// line in the status area. The line appears when the span is first entered, is updated when one
+/// of the fields is recorded with `Span::record`, and disappears when the span is closed. The
+/// lines are rendered by the `ProgressSpans` widget that is returned by `spans`, which can be
+/// combined with other widgets using `Lines`.
+///
+/// ```
+/// use tracing::info_span;
+///
+/// let span = info_span!("download", status.msg = "Downloading", status.pos = 0, status.len = 100);
+/// let _entered = span.enter();
+///
+/// span.record("status.pos", 50);
+/// ```
+///
+/// The number of rows taken up by the status area is measured every time it is drawn, so lines
+/// can come and go freely. By default the status area is only redrawn after log messages are
+/// written or when the refresh interval passes. Use `redraw` to redraw it whenever a line changes
+/// instead. Position updates are usually recorded in a hot loop, so they redraw the status area at
+/// most once per `redraw_interval`.
+///
+/// The layer should be added to a `tracing_subscriber::Registry` alongside the formatting layer
+/// that writes to the status line handler.
+pub struct ProgressLayer {
+    /// The shared progress state.
+    spans: ProgressSpans,
+
+    /// A handle that is used to redraw the status area when a line changes.
+    handle: Option<StatusHandle>,
+
+    /// The minimum time between two redraws caused by recorded field values.
+    redraw_interval: Duration,
+
+    /// The moment at which the status area was last redrawn by this layer.
+    redrawn: Mutex<Option<Instant>>,
+}
+
+impl ProgressLayer {
+    /// Create a new layer.
+    pub fn new() -> Self {
+        Self {
+            spans: ProgressSpans::default(),
+            handle: None,
+            redraw_interval: Duration::from_millis(50),
+            redrawn: Mutex::new(None),
+        }
+    }
+
+    /// Redraw the status area using the provided handle whenever a line appears, changes, or
+    /// disappears.
+    pub fn redraw(mut self, handle: StatusHandle) -> Self {
+        self.handle = Some(handle);
+        self
+    }
+
+    /// Set the minimum time between two redraws caused by `Span::record`. Changes that are recorded
+    /// sooner are shown by the next redraw, e.g. when a line appears or disappears, after a log
+    /// message, or when the refresh interval passes. The default is 50 milliseconds.
+    ///
+    /// Lines that appear or disappear always redraw the status area immediately.
+    pub fn redraw_interval(mut self, interval: Duration) -> Self {
+        self.redraw_interval = interval;
+        self
+    }
+
+    /// Return the widget that renders the spans tracked by this layer.
+    pub fn spans(&self) -> ProgressSpans {
+        self.spans.clone()
+    }
+
+    /// Redraw the status area if the progress state changed and a handle was provided. When
+    /// `throttle` is true the redraw is skipped if the previous one happened less than the redraw
+    /// interval ago.
+    fn changed(&self, changed: bool, throttle: bool) {
+        let (true, Some(handle)) = (changed, &self.handle) else {
+            return;
+        };
+
+        let now = Instant::now();
+        let mut redrawn = self.redrawn.lock().expect("Progress redraw mutex was poisoned");
+
+        if throttle && redrawn.is_some_and(|redrawn| now.saturating_duration_since(redrawn) < self.redraw_interval) {
+            return;
+        }
+
+        *redrawn = Some(now);
+        drop(redrawn);
+
+        // The handler may have shut down, there is nothing to redraw in that case
+        let _ = handle.redraw();
+    }
+}
+
+impl Default for ProgressLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for ProgressLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let fields = attrs.metadata().fields();
+
+        if ![MESSAGE_FIELD, POSITION_FIELD, LENGTH_FIELD].iter().any(|name| fields.field(name).is_some()) {
+            return;
+        }
+
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut fields = ProgressFields::default();
+        attrs.record(&mut fields);
+
+        span.extensions_mut().insert(fields);
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut extensions = span.extensions_mut();
+
+        if let Some(fields) = extensions.get_mut::<ProgressFields>() {
+            values.record(fields);
+            let changed = self.spans.update(id, fields);
+
+            drop(extensions);
+            self.changed(changed, true);
+        }
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let Some(fields) = span.extensions().get::<ProgressFields>().cloned() else {
+            return;
+        };
+
+        self.changed(self.spans.show(ProgressSpan {
+            id: id.clone(),
+            name: span.name(),
+            target: span.metadata().target(),
+            fields,
+            started: Instant::now(),
+        }), false);
+    }
+
+    fn on_close(&self, id: span::Id, _ctx: Context<'_, S>) {
+        self.changed(self.spans.hide(&id), false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tracing::info_span;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::widgets::{Lines, Text};
+
+    use super::*;
+
+    /// Render the provided widget into a string.
+    fn render(widget: &mut impl Widget) -> String {
+        let mut output = StatusWriter::new(80, 24);
+        widget.render(&mut output).unwrap();
+
+        String::from_utf8(output.as_bytes().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn spans_are_shown_while_they_are_open() {
+        let layer = ProgressLayer::new();
+        let mut spans = layer.spans().width(4);
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let download = info_span!("download", status.pos = 0, status.len = 4);
+            let compile = info_span!("compile", status.msg = tracing::field::Empty);
+            let untracked = info_span!("untracked");
+
+            assert_eq!(render(&mut spans), "");
+
+            let _entered = download.enter();
+            compile.in_scope(|| compile.record("status.msg", "compiling foo"));
+            untracked.in_scope(|| ());
+            download.record("status.pos", 2);
+
+            assert_eq!(render(&mut spans), "download [=>  ] 2/4\ncompiling foo");
+
+            drop(compile);
+
+            assert_eq!(render(&mut spans), "download [=>  ] 2/4");
+        });
+
+        assert_eq!(render(&mut spans), "");
+    }
+
+    #[test]
+    fn recorded_positions_redraw_at_most_once_per_interval() {
+        let draws = Arc::new(AtomicUsize::new(0));
+        let counted = draws.clone();
+
+        let handler = crate::Builder::new(io::sink())
+            .with_callback(move |_: &mut StatusWriter| {
+                counted.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            })
+            .terminal_size(80, 24)
+            .force_terminal(true)
+            .finish();
+
+        let layer = ProgressLayer::new()
+            .redraw(handler.handle())
+            .redraw_interval(Duration::from_secs(60));
+
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = info_span!("download", status.pos = 0, status.len = 1000);
+
+            span.in_scope(|| {
+                for position in 1..=1000 {
+                    span.record("status.pos", position);
+                }
+            });
+        });
+
+        // One draw when the line appears, and one when it disappears
+        assert_eq!(draws.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn empty_spans_take_up_no_lines() {
+        let layer = ProgressLayer::new();
+        let mut status = Lines::new()
+            .push(Text::new("title"))
+            .push(layer.spans())
+            .push(Text::new("footer"));
+
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            assert_eq!(render(&mut status), "title\nfooter");
+
+            info_span!("job", status.msg = "working").in_scope(|| {
+                assert_eq!(render(&mut status), "title\nworking\nfooter");
+            });
+        });
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// The number of samples per window at which new samples start to be merged with the most recent
+/// one, which bounds the memory used by fast-moving progress.
+const SAMPLES_PER_WINDOW: u32 = 64;
+
+/// An estimator for the rate of progress and the time remaining, based on the position updates
+/// seen over a sliding window.
+///
+/// Feed the estimator the current position whenever it changes or whenever the status line is
+/// drawn, and read the rate in items (or bytes) per second and the estimated time remaining back.
+/// Only the updates within the window are taken into account, so the estimate follows changes in
+/// speed without jumping around on every update. The window is 5 seconds by default.
+///
+/// The estimator is a plain value without shared state, so that it can be kept inside the state of
+/// a status line callback or behind the mutex of a shared state struct. Use the types in the
+/// `format` module to show the results, e.g. `HumanByteRate` and `HumanDuration`. The `Rate` and
+/// `Eta` widgets wrap an estimator for a `ProgressBar`.
+///
+/// ```
+/// use std::time::{Duration, Instant};
+///
+/// use tracing_statusbar::RateEstimator;
+///
+/// let start = Instant::now();
+/// let mut estimator = RateEstimator::new();
+///
+/// estimator.update_at(0, start);
+/// estimator.update_at(50, start + Duration::from_secs(1));
+///
+/// assert_eq!(estimator.rate(), Some(50.0));
+/// assert_eq!(estimator.eta(100), Some(Duration::from_secs(1)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct RateEstimator {
+    /// The positions seen within the window along with the moments at which they were seen, from
+    /// oldest to newest. The oldest sample may be older than the window, so that a rate can still
+    /// be estimated when updates are further apart than the window.
+    samples: VecDeque<(Instant, u64)>,
+
+    /// The length of the sliding window.
+    window: Duration,
+}
+
+impl RateEstimator {
+    /// Create a new estimator with the default window of 5 seconds.
+    pub fn new() -> Self {
+        Self {
+            samples: VecDeque::new(),
+            window: Duration::from_secs(5),
+        }
+    }
+
+    /// Set the length of the sliding window. Longer windows give steadier estimates, while shorter
+    /// windows follow changes in speed more quickly.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Record the current position.
+    pub fn update(&mut self, position: u64) {
+        self.update_at(position, Instant::now());
+    }
+
+    /// Record the position at the provided moment. Moments must not go back in time.
+    ///
+    /// When the position goes back, e.g. because a transfer restarted, all earlier samples are
+    /// discarded.
+    pub fn update_at(&mut self, position: u64, now: Instant) {
+        if self.position().is_some_and(|last| position < last) {
+            self.samples.clear();
+        }
+
+        // Merge samples that are very close together, so that the number of samples stays bounded
+        let len = self.samples.len();
+        let resolution = self.window / SAMPLES_PER_WINDOW;
+
+        if len >= 2 && now.saturating_duration_since(self.samples[len - 2].0) < resolution {
+            self.samples[len - 1] = (now, position);
+        } else {
+            self.samples.push_back((now, position));
+        }
+
+        // Keep a single sample from before the window as the starting point of the estimate
+        while self.samples.len() > 2 && now.saturating_duration_since(self.samples[1].0) >= self.window {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Discard all samples, e.g. when the progress is restarted.
+    pub fn reset(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Return the most recently recorded position, or `None` when no position was recorded.
+    pub fn position(&self) -> Option<u64> {
+        self.samples.back().map(|(_, position)| *position)
+    }
+
+    /// Return the estimated rate in items per second, or `None` when fewer than two positions were
+    /// recorded.
+    pub fn rate(&self) -> Option<f64> {
+        let (first, first_position) = self.samples.front()?;
+        let (last, last_position) = self.samples.back()?;
+
+        let elapsed = last.saturating_duration_since(*first).as_secs_f64();
+
+        if elapsed > 0.0 {
+            Some((last_position - first_position) as f64 / elapsed)
+        } else {
+            None
+        }
+    }
+
+    /// Return the estimated time until the provided length is reached, or `None` when no progress
+    /// is being made.
+    pub fn eta(&self, length: u64) -> Option<Duration> {
+        let remaining = length.saturating_sub(self.position()?);
+
+        if remaining == 0 {
+            return Some(Duration::ZERO);
+        }
+
+        match self.rate()? {
+            rate if rate > 0.0 => Duration::try_from_secs_f64(remaining as f64 / rate).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl Default for RateEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_follows_the_window() {
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+        let mut estimator = RateEstimator::new().window(Duration::from_secs(4));
+
+        assert_eq!(estimator.rate(), None);
+
+        // Ten items per second for a while
+        for second in 0..=10 {
+            estimator.update_at(second * 10, at(second));
+        }
+
+        assert_eq!(estimator.rate(), Some(10.0));
+        assert_eq!(estimator.eta(200), Some(Duration::from_secs(10)));
+
+        // The rate drops to two items per second, earlier samples leave the window
+        for second in 11..=20 {
+            estimator.update_at(100 + (second - 10) * 2, at(second));
+        }
+
+        assert_eq!(estimator.rate(), Some(2.0));
+        assert_eq!(estimator.eta(120), Some(Duration::ZERO));
+
+        // Updates that are further apart than the window still give an estimate
+        estimator.update_at(140, at(30));
+
+        assert_eq!(estimator.rate(), Some(2.0));
+
+        // Going back restarts the estimate
+        estimator.update_at(0, at(31));
+
+        assert_eq!(estimator.rate(), None);
+        assert_eq!(estimator.eta(100), None);
+    }
+}
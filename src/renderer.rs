@@ -0,0 +1,1967 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::Command;
+use crossterm::cursor::{MoveTo, MoveToColumn, MoveUp, RestorePosition, SavePosition, Show};
+use crossterm::style::{ContentStyle, ResetColor};
+use crossterm::terminal::{self, BeginSynchronizedUpdate, Clear, ClearType, EndSynchronizedUpdate};
+use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use tracing::Level;
+use tracing::callsite::Identifier;
+
+use crate::{ErrorPolicy, RawModeGuard, StatusWriter, ansi, utils};
+use crate::builder::Settings;
+use crate::error::HealthState;
+use crate::pager::{Pager, PagerCommand};
+
+/// The terminal size that is assumed when the size cannot be determined.
+const FALLBACK_SIZE: (u16, u16) = (80, 24);
+
+/// The way in which the status area is kept below the log messages.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Erase the status area before writing log messages, and draw it again below them. This
+    /// works on any terminal, but the status area is redrawn after every batch of log messages.
+    #[default]
+    Inline,
+
+    /// Pin the status area to the bottom rows of the terminal using a scroll region (DECSTBM).
+    ///
+    /// Log messages scroll within the region above the status area, so they can be written
+    /// without touching the status area. The status area is only repainted when its contents
+    /// change, which avoids flicker under heavy logging. The scroll region is adjusted when the
+    /// terminal is resized or the status area changes height, and it is reset when the handler is
+    /// dropped.
+    ScrollRegion,
+}
+
+/// Whether frames are wrapped in synchronized updates (DEC private mode 2026).
+///
+/// Terminals that support synchronized updates postpone rendering until the end of the update, so
+/// that a status area is never shown half erased. Terminals that do not support them ignore the
+/// escape sequences.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SynchronizedOutput {
+    /// Use synchronized updates when the terminal is known to support them. Support is detected
+    /// using the `TERM`, `TERM_PROGRAM`, and `WT_SESSION` environment variables.
+    #[default]
+    Auto,
+
+    /// Always use synchronized updates.
+    Enabled,
+
+    /// Never use synchronized updates.
+    Disabled,
+}
+
+impl SynchronizedOutput {
+    /// Return true when synchronized updates should be used.
+    fn enabled(self) -> bool {
+        match self {
+            Self::Auto => utils::supports_synchronized_output(),
+            Self::Enabled => true,
+            Self::Disabled => false,
+        }
+    }
+}
+
+/// A closure that renders a final summary line.
+pub type SummaryFn = dyn Fn(&mut StatusWriter) -> io::Result<()> + Send + Sync;
+
+/// What to do with the status area when the log handler shuts down.
+#[derive(Clone, Default)]
+pub enum FinishMode {
+    /// Erase the status area.
+    Clear,
+
+    /// Leave the last status area in place, and move the cursor to a fresh line below it.
+    #[default]
+    Keep,
+
+    /// Replace the status area with the output of the provided closure, e.g. a line such as
+    /// `Finished in 12.3s, 4 warnings`. The cursor is moved to a fresh line below it.
+    Summary(Arc<SummaryFn>),
+}
+
+impl FinishMode {
+    /// Create a finish mode that replaces the status area with the output of the provided closure.
+    pub fn summary<F>(summary: F) -> Self
+    where
+        F: Fn(&mut StatusWriter) -> io::Result<()> + Send + Sync + 'static,
+    {
+        Self::Summary(Arc::new(summary))
+    }
+}
+
+impl fmt::Debug for FinishMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Clear => f.write_str("Clear"),
+            Self::Keep => f.write_str("Keep"),
+            Self::Summary(_) => f.write_str("Summary(..)"),
+        }
+    }
+}
+
+/// A log entry that can be written by the renderer.
+///
+/// Entries written through `MakeWriter::make_writer_for` carry the level, target, and callsite of
+/// the event that produced them. Other entries, such as panic messages, have none of these.
+pub(crate) trait Entry {
+    /// Return the formatted log entry.
+    fn bytes(&self) -> &[u8];
+
+    /// Return the level of the event that produced the entry, if known.
+    fn level(&self) -> Option<Level> {
+        None
+    }
+
+    /// Return the target of the event that produced the entry, if known.
+    fn target(&self) -> Option<&str> {
+        None
+    }
+
+    /// Return the callsite of the event that produced the entry, if known.
+    fn callsite(&self) -> Option<&Identifier> {
+        None
+    }
+}
+
+impl Entry for [u8] {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Entry for str {
+    fn bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<T: Entry + ?Sized> Entry for &T {
+    fn bytes(&self) -> &[u8] {
+        (**self).bytes()
+    }
+
+    fn level(&self) -> Option<Level> {
+        (**self).level()
+    }
+
+    fn target(&self) -> Option<&str> {
+        (**self).target()
+    }
+
+    fn callsite(&self) -> Option<&Identifier> {
+        (**self).callsite()
+    }
+}
+
+/// A borrowed log entry, along with the level, target, and callsite of the event that produced it.
+pub(crate) struct TaggedEntry<'a> {
+    /// The formatted log entry.
+    pub bytes: &'a [u8],
+
+    /// The level, target, and callsite of the event, if known.
+    pub event: Option<(Level, &'a str, &'a Identifier)>,
+}
+
+impl Entry for TaggedEntry<'_> {
+    fn bytes(&self) -> &[u8] {
+        self.bytes
+    }
+
+    fn level(&self) -> Option<Level> {
+        self.event.map(|(level, _, _)| level)
+    }
+
+    fn target(&self) -> Option<&str> {
+        self.event.map(|(_, target, _)| target)
+    }
+
+    fn callsite(&self) -> Option<&Identifier> {
+        self.event.map(|(_, _, callsite)| callsite)
+    }
+}
+
+/// A note about log entries that were discarded by the rate limit, written as a log entry of its
+/// own.
+struct Note {
+    /// The formatted note, ending with a newline.
+    text: String,
+
+    /// The level of the callsite whose entries were discarded.
+    level: Level,
+
+    /// The target of the callsite whose entries were discarded.
+    target: String,
+}
+
+impl Entry for Note {
+    fn bytes(&self) -> &[u8] {
+        self.text.as_bytes()
+    }
+
+    fn level(&self) -> Option<Level> {
+        Some(self.level)
+    }
+
+    fn target(&self) -> Option<&str> {
+        Some(&self.target)
+    }
+}
+
+/// The rate limiting state of a single callsite.
+struct Limit {
+    /// The start of the current interval.
+    start: Instant,
+
+    /// The number of entries written in the current interval.
+    written: u32,
+
+    /// The number of entries discarded in the current interval.
+    suppressed: u64,
+
+    /// The level of the callsite.
+    level: Level,
+
+    /// The target of the callsite.
+    target: String,
+}
+
+impl Limit {
+    /// Return a note about the discarded entries, if any entries were discarded.
+    fn note(&self) -> Option<Note> {
+        let plural = if self.suppressed == 1 { "" } else { "s" };
+
+        (self.suppressed > 0).then(|| Note {
+            text: format!("suppressed {} similar message{plural} from {}\n", self.suppressed, self.target),
+            level: self.level,
+            target: self.target.clone(),
+        })
+    }
+}
+
+/// A transient notification, shown in a row of its own below the status lines until it expires.
+pub(crate) struct Toast {
+    /// The message, without newlines.
+    message: String,
+
+    /// The moment at which the notification disappears.
+    expires: Instant,
+}
+
+impl Toast {
+    /// Create a notification that shows the provided message for the provided duration. Newlines
+    /// in the message are replaced with spaces, so that it fits in a single row.
+    pub fn new(message: &str, duration: Duration) -> Self {
+        Self {
+            message: message.lines().collect::<Vec<_>>().join(" "),
+            expires: Instant::now() + duration,
+        }
+    }
+}
+
+/// The log entry that was written most recently, used to collapse repeats.
+#[derive(Clone)]
+struct Repeat {
+    /// The callsite of the event that produced the entry, if known.
+    callsite: Option<Identifier>,
+
+    /// The entry without escape sequences and without its leading timestamp, see `repeat_key`.
+    key: String,
+
+    /// The number of times the entry was written in a row.
+    count: u64,
+
+    /// The widths of the lines of the entry as it is currently shown, including the repeat count.
+    line_widths: Vec<usize>,
+}
+
+/// Return true when a word looks like a timestamp, e.g. `2024-05-01T12:00:00.123456Z`, `12:00:00`,
+/// or `0.123456s` as written by the timers of `tracing_subscriber`.
+fn is_timestamp(word: &str) -> bool {
+    if !word.starts_with(|c: char| c.is_ascii_digit()) {
+        return false;
+    }
+
+    let uptime = word.strip_suffix('s')
+        .is_some_and(|seconds| seconds.chars().all(|c| c.is_ascii_digit() || c == '.'));
+
+    let time = word.contains([':', '-'])
+        && word.chars().all(|c| c.is_ascii_digit() || "-:.,+TZ".contains(c));
+
+    uptime || time
+}
+
+/// Return the part of a log entry that is compared to decide whether it repeats the previous
+/// entry: the entry without escape sequences and without the timestamps at its start, which differ
+/// for every entry.
+fn repeat_key(entry: &[u8]) -> String {
+    let text = ansi::strip(&String::from_utf8_lossy(entry));
+    let mut rest = text.trim_start();
+
+    while let Some((_, after)) = rest.split_once(char::is_whitespace).filter(|(word, _)| is_timestamp(word)) {
+        rest = after.trim_start();
+    }
+
+    rest.to_owned()
+}
+
+/// Return the widths of the lines of a log entry, ignoring escape sequences.
+fn line_widths(entry: &[u8]) -> Vec<usize> {
+    String::from_utf8_lossy(entry)
+        .lines()
+        .map(ansi::width)
+        .collect()
+}
+
+/// A secondary writer for the log entries of selected targets.
+#[derive(Clone)]
+pub(crate) struct Route {
+    /// The target prefix of the routed entries.
+    pub prefix: String,
+
+    /// The writer that routed entries are written to.
+    pub output: Arc<Mutex<dyn Write + Send>>,
+}
+
+/// A command that sets the scroll region of the terminal (DECSTBM) to the provided range of
+/// 1-based rows, or resets it to the full screen when the range is `None`.
+///
+/// Note that terminals move the cursor to the home position when the scroll region changes.
+struct SetScrollRegion(Option<(u16, u16)>);
+
+impl Command for SetScrollRegion {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        match self.0 {
+            Some((top, bottom)) => write!(f, "\x1b[{top};{bottom}r"),
+            None => write!(f, "\x1b[r"),
+        }
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "Scroll regions require ANSI support"))
+    }
+}
+
+/// What the terminal shows according to the renderer, i.e. the state that is used to erase or
+/// update the output that was written previously.
+///
+/// Building a frame updates this state for the output in the frame. It is restored when writing
+/// the frame fails, because the previous output is still shown in that case.
+struct Shown {
+    /// The widths of the lines of the inline status area.
+    line_widths: Vec<usize>,
+
+    /// The layout of the pinned status area.
+    region: Option<Region>,
+
+    /// The output of the pinned status area.
+    painted: Vec<u8>,
+
+    /// The log entry that was written most recently.
+    repeat: Option<Repeat>,
+}
+
+/// The layout of a pinned status area.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Region {
+    /// The terminal size, as columns and rows, for which the scroll region was set up.
+    size: (u16, u16),
+
+    /// The number of rows at the bottom of the terminal reserved for the status area.
+    rows: u16,
+}
+
+/// Return the number of terminal rows taken up by lines of the given widths, when shown on a
+/// terminal with the given number of columns.
+///
+/// A line that exactly fills the width of the terminal does not wrap, because the cursor stays on
+/// the last column until the next character is written.
+fn physical_rows(line_widths: &[usize], columns: u16) -> usize {
+    let columns = usize::from(columns.max(1));
+
+    line_widths
+        .iter()
+        .map(|width| width.div_ceil(columns).max(1))
+        .sum()
+}
+
+/// A status area renderer.
+///
+/// The renderer owns the output writer and the status line callback, and keeps track of the status
+/// lines that are currently shown. It is used by both the threaded and the unthreaded log handlers
+/// to write log entries above the status area and to redraw the status area afterwards.
+pub(crate) struct Renderer<T, W>
+where
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
+    W: Write,
+{
+    /// The status line callback that will be invoked after every batch of log messages.
+    callback: T,
+
+    /// The output writer used to write log messages and status lines to.
+    output: W,
+
+    /// When true the wrapped writer is assumed to be a terminal that is using raw mode. This will
+    /// ensure that the raw mode is temporarily disabled when writing log messages. This prevents
+    /// screen corruption.
+    assume_raw_mode: bool,
+
+    /// When false the output is not a terminal. Log entries are passed through as is, and the
+    /// status line is never drawn.
+    terminal: bool,
+
+    /// The way in which the status area is drawn.
+    mode: RenderMode,
+
+    /// When true every frame is wrapped in a synchronized update.
+    synchronized: bool,
+
+    /// The buffer in which a frame is assembled before it is written to the output.
+    frame: Vec<u8>,
+
+    /// What to do when writing to the output or invoking the status line callback fails.
+    error_policy: ErrorPolicy,
+
+    /// What to do with the status area when the log handler shuts down.
+    on_finish: FinishMode,
+
+    /// The health of the log handler, updated whenever an error occurs.
+    health: Arc<HealthState>,
+
+    /// The styles applied to log entries of specific levels.
+    level_styles: Vec<(Level, ContentStyle)>,
+
+    /// The secondary writers for log entries of selected targets.
+    routes: Vec<Route>,
+
+    /// The current layout of the pinned status area when using `RenderMode::ScrollRegion`, or
+    /// `None` when no scroll region was set up yet.
+    region: Option<Region>,
+
+    /// The status area output that was painted most recently when using
+    /// `RenderMode::ScrollRegion`. The status area is only repainted when its output changes.
+    painted: Vec<u8>,
+
+    /// A fixed terminal size. The size is queried from the terminal when this is `None`.
+    terminal_size: Option<(u16, u16)>,
+
+    /// The writer that the status line callback writes to. It is kept around so that its buffer
+    /// can be reused.
+    status: StatusWriter,
+
+    /// The widths of the lines written in the previous invocation of the status line callback.
+    /// This is used to properly clean up the previous status lines when a new log message should
+    /// be written. The widths are stored instead of a row count, so that the number of rows can
+    /// be recomputed when the terminal is resized and the terminal rewraps the status lines.
+    line_widths: Vec<usize>,
+
+    /// The interval at which the status is printed as a log line when the output is not a
+    /// terminal.
+    heartbeat: Option<Duration>,
+
+    /// The moment at which the next heartbeat is due.
+    next_heartbeat: Instant,
+
+    /// The status text that was printed by the most recent heartbeat. A heartbeat is skipped when
+    /// the status text did not change.
+    last_heartbeat: String,
+
+    /// The most recent log entries without escape sequences, oldest first.
+    history: VecDeque<String>,
+
+    /// The maximum number of log entries kept in the history.
+    history_capacity: usize,
+
+    /// The history pager, while it is shown on the alternate screen.
+    pager: Option<Pager>,
+
+    /// The log entries that arrived while the pager was shown, to be written when it closes.
+    held: Vec<u8>,
+
+    /// When true consecutive identical log entries are collapsed into a single line.
+    collapse_repeats: bool,
+
+    /// The log entry that was written to the terminal most recently, when collapsing repeats.
+    repeat: Option<Repeat>,
+
+    /// The maximum number of log entries per callsite, and the interval over which they are
+    /// counted.
+    rate_limit: Option<(u32, Duration)>,
+
+    /// The rate limiting state of the callsites that wrote entries in their current interval.
+    limits: HashMap<Identifier, Limit>,
+
+    /// The notifications that are shown below the status lines, oldest first.
+    toasts: Vec<Toast>,
+
+    /// The maximum number of rows of the status area, in addition to the height of the terminal.
+    max_status_lines: Option<usize>,
+}
+
+impl<T, W> Renderer<T, W>
+where
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
+    W: Write,
+{
+    /// Initialize a new renderer using the provided status line callback, output writer, and
+    /// settings.
+    pub fn new(callback: T, output: W, settings: &Settings) -> Self {
+        Self {
+            callback,
+            output,
+            assume_raw_mode: settings.assume_raw_mode,
+            terminal: settings.terminal(),
+            mode: settings.render_mode,
+            synchronized: settings.synchronized_output.enabled(),
+            frame: Vec::new(),
+            error_policy: settings.error_policy.clone(),
+            on_finish: settings.on_finish.clone(),
+            health: settings.health.clone(),
+            level_styles: settings.level_styles.clone(),
+            routes: settings.routes.clone(),
+            region: None,
+            painted: Vec::new(),
+            terminal_size: settings.terminal_size,
+            status: StatusWriter::new(FALLBACK_SIZE.0, FALLBACK_SIZE.1),
+            line_widths: Vec::new(),
+            heartbeat: settings.heartbeat,
+            next_heartbeat: Instant::now() + settings.heartbeat.unwrap_or_default(),
+            last_heartbeat: String::new(),
+            history: VecDeque::new(),
+            history_capacity: settings.history,
+            pager: None,
+            held: Vec::new(),
+            collapse_repeats: settings.collapse_repeats,
+            repeat: None,
+            rate_limit: settings.rate_limit,
+            limits: HashMap::new(),
+            toasts: Vec::new(),
+            max_status_lines: settings.max_status_lines,
+        }
+    }
+
+    /// Return the current size of the terminal as columns and rows.
+    ///
+    /// Some pseudo terminals report a size of zero, which is treated as an unknown size.
+    fn size(&self) -> (u16, u16) {
+        self.terminal_size
+            .or_else(|| terminal::size().ok())
+            .filter(|(columns, rows)| *columns > 0 && *rows > 0)
+            .unwrap_or(FALLBACK_SIZE)
+    }
+
+    /// Erase the status lines that were written by the previous invocation of the status line
+    /// callback, leaving the cursor at the start of the line where the status area began.
+    fn clear(&mut self) -> io::Result<()> {
+        let (columns, _) = self.size();
+        let rows = physical_rows(&self.line_widths, columns);
+
+        // Move to the beginning of the line and reset the color to default
+        crossterm::queue!(
+            self.frame,
+            MoveToColumn(0),
+            ResetColor,
+        )?;
+
+        // Move to the first row of the status area. Note that a zero row movement is interpreted
+        // as a single row by terminals.
+        if rows > 1 {
+            crossterm::queue!(
+                self.frame,
+                MoveUp((rows - 1).try_into().unwrap_or(u16::MAX)),
+            )?;
+        }
+
+        // Erase the status area
+        crossterm::queue!(
+            self.frame,
+            Clear(ClearType::FromCursorDown),
+        )?;
+
+        self.line_widths.clear();
+
+        Ok(())
+    }
+
+    /// Invoke the status line callback and add its output to the frame.
+    fn draw(&mut self) -> io::Result<()> {
+        let size = self.size();
+
+        // Render the status line and track the width of the lines written
+        self.render_status(size)?;
+        self.place_status()
+    }
+
+    /// Invoke the status line callback for a terminal of the provided size, followed by a row for
+    /// every notification that has not expired yet.
+    fn render_status(&mut self, (columns, rows): (u16, u16)) -> io::Result<()> {
+        self.status.reset(columns, rows);
+        (self.callback)(&mut self.status)?;
+
+        let now = Instant::now();
+        self.toasts.retain(|toast| toast.expires > now);
+
+        for toast in &self.toasts {
+            let output = self.status.as_bytes();
+
+            if !output.is_empty() && !output.ends_with(b"\n") {
+                self.status.write_all(b"\n")?;
+            }
+
+            let message = ansi::truncate(&toast.message, columns.into(), "…");
+            self.status.write_all(message.as_bytes())?;
+        }
+
+        // Keep at least one row for log messages, so that the previous status area can always be
+        // reached when erasing it
+        let max_rows = usize::from(rows.saturating_sub(1)).max(1);
+        let max_rows = self.max_status_lines.map_or(max_rows, |lines| lines.clamp(1, max_rows));
+
+        self.limit_status(columns, max_rows);
+
+        Ok(())
+    }
+
+    /// Cut the rendered status lines so that they take up at most the provided number of rows,
+    /// keeping the last row for a line that says how many lines were cut.
+    ///
+    /// The first line is always kept. When it does not leave a row for the indicator by itself, it
+    /// is trimmed to the rows that are left, and the indicator is dropped when no rows are left.
+    fn limit_status(&mut self, columns: u16, max_rows: usize) {
+        let widths = self.status.line_widths();
+
+        if physical_rows(&widths, columns) <= max_rows {
+            return;
+        }
+
+        let lines = (1..widths.len())
+            .rev()
+            .find(|lines| physical_rows(&widths[..*lines], columns) < max_rows);
+
+        match lines {
+            Some(lines) => self.status.cut_lines(lines),
+
+            None => {
+                let indicator = widths.len() > 1 && max_rows > 1;
+                let rows = max_rows - usize::from(indicator);
+
+                self.status.cut_first_line(usize::from(columns) * rows, indicator);
+            }
+        }
+    }
+
+    /// Add the rendered contents of the status writer to the frame, at the start of the current
+    /// line.
+    fn place_status(&mut self) -> io::Result<()> {
+        self.line_widths = self.status.line_widths();
+
+        crossterm::queue!(
+            self.frame,
+            MoveToColumn(0),
+        )?;
+
+        self.frame.extend_from_slice(self.status.as_bytes());
+
+        Ok(())
+    }
+
+    /// Add the status to the frame as a plain log line if a heartbeat is due and the status text
+    /// changed since the previous heartbeat.
+    fn heartbeat(&mut self) -> io::Result<()> {
+        let Some(interval) = self.heartbeat else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+
+        if now < self.next_heartbeat {
+            return Ok(());
+        }
+
+        self.next_heartbeat = now + interval;
+
+        let (columns, rows) = self.size();
+        self.status.reset(columns, rows);
+        (self.callback)(&mut self.status)?;
+
+        let text = self.plain_status();
+
+        if text.is_empty() || text == self.last_heartbeat {
+            return Ok(());
+        }
+
+        self.frame.extend_from_slice(text.as_bytes());
+        self.last_heartbeat = text;
+
+        Ok(())
+    }
+
+    /// Return the rendered contents of the status writer as plain text, without escape sequences
+    /// and empty lines. Every line ends with a newline.
+    fn plain_status(&self) -> String {
+        let status = ansi::strip(&String::from_utf8_lossy(self.status.as_bytes()));
+        let mut text = String::new();
+
+        for line in status.lines().map(str::trim_end).filter(|line| !line.is_empty()) {
+            text.push_str(line);
+            text.push('\n');
+        }
+
+        text
+    }
+
+    /// Add log entries to the frame, returning true if any entries were added to the frame.
+    ///
+    /// Entries of routed targets are written to their secondary writer instead. Writing the
+    /// remaining entries continues when a secondary writer fails, and the first error is returned
+    /// along with the result. The status writer is told about the most severe level of the entries.
+    ///
+    /// Entries that exceed the rate limit of their callsite are discarded. Notes about discarded
+    /// entries are written once the interval in which they were discarded has passed.
+    fn write_entries<I>(&mut self, entries: I) -> (bool, io::Result<()>)
+    where
+        I: IntoIterator,
+        I::Item: Entry,
+    {
+        let mut result = Ok(());
+        let mut severity = None;
+
+        let now = Instant::now();
+        let mut written = self.write_notes(Some(now), &mut result);
+
+        for entry in entries {
+            severity = match (severity, entry.level()) {
+                (Some(current), Some(level)) => Some(Level::min(current, level)),
+                (current, level) => current.or(level),
+            };
+
+            if self.allow(&entry, now) {
+                written |= self.write_entry(&entry, &mut result);
+            }
+        }
+
+        self.status.set_severity(severity);
+
+        (written, result)
+    }
+
+    /// Add a log entry to the frame, returning true if it was added to the frame.
+    ///
+    /// Entries of routed targets are written to their secondary writer instead. The first error of
+    /// a secondary writer is kept in `result`. An entry that repeats the previous one replaces it
+    /// when collapsing repeats.
+    fn write_entry(&mut self, entry: &impl Entry, result: &mut io::Result<()>) -> bool {
+        let route = entry.target()
+            .and_then(|target| self.routes.iter().find(|route| utils::matches_target(target, &route.prefix)));
+
+        if let Some(route) = route {
+            let mut output = route.output.lock().expect("Route writer mutex was poisoned");
+            let routed = output.write_all(entry.bytes()).and_then(|()| output.flush());
+
+            if result.is_ok() {
+                *result = routed;
+            }
+
+            return false;
+        }
+
+        let style = entry.level()
+            .filter(|_| self.terminal)
+            .and_then(|level| self.level_styles.iter().find(|(styled, _)| *styled == level))
+            .map(|(_, style)| *style);
+
+        let collapse = self.collapse_repeats && self.terminal;
+        let key = collapse.then(|| repeat_key(entry.bytes()));
+
+        let repeated = self.repeat.as_ref().is_some_and(|repeat| {
+            repeat.callsite.as_ref() == entry.callsite() && Some(&repeat.key) == key.as_ref()
+        });
+
+        if repeated {
+            self.collapse(entry.bytes(), style);
+            return true;
+        }
+
+        match style {
+            Some(style) => self.write_styled(entry.bytes(), style),
+            None => self.frame.extend_from_slice(entry.bytes()),
+        }
+
+        if let Some(key) = key {
+            self.repeat = Some(Repeat {
+                callsite: entry.callsite().cloned(),
+                key,
+                count: 1,
+                line_widths: line_widths(entry.bytes()),
+            });
+        }
+
+        self.record(entry.bytes());
+
+        true
+    }
+
+    /// Replace the previous log entry with the provided entry that repeats it, followed by how
+    /// many times it was repeated. The entry is shown as is, so its timestamp is the most recent
+    /// one.
+    ///
+    /// The cursor is expected to be at the start of the line below the previous entry, which is
+    /// where the next log entry would be written.
+    fn collapse(&mut self, entry: &[u8], style: Option<ContentStyle>) {
+        let (columns, _) = self.size();
+
+        let Some(repeat) = &mut self.repeat else {
+            return;
+        };
+
+        let rows = physical_rows(&repeat.line_widths, columns);
+        repeat.count += 1;
+
+        let entry = String::from_utf8_lossy(entry);
+        let line = entry.strip_suffix('\n').unwrap_or(&entry);
+        let text = format!("{line} (repeated {} times)\n", repeat.count);
+
+        repeat.line_widths = line_widths(text.as_bytes());
+
+        // Writing to a vector can not fail
+        let _ = crossterm::queue!(
+            self.frame,
+            MoveUp(u16::try_from(rows).unwrap_or(u16::MAX)),
+            MoveToColumn(0),
+            Clear(ClearType::FromCursorDown),
+        );
+
+        match style {
+            Some(style) => self.write_styled(text.as_bytes(), style),
+            None => self.frame.extend_from_slice(text.as_bytes()),
+        }
+
+        // Clearing below the entry also erased a pinned status area
+        self.painted.clear();
+    }
+
+    /// Count a log entry against the rate limit of its callsite, returning true if it may be
+    /// written.
+    fn allow(&mut self, entry: &impl Entry, now: Instant) -> bool {
+        let (Some((messages, _)), Some(callsite)) = (self.rate_limit, entry.callsite()) else {
+            return true;
+        };
+
+        let limit = self.limits.entry(callsite.clone()).or_insert_with(|| Limit {
+            start: now,
+            written: 0,
+            suppressed: 0,
+            level: entry.level().unwrap_or(Level::INFO),
+            target: entry.target().unwrap_or_default().to_owned(),
+        });
+
+        if limit.written < messages {
+            limit.written += 1;
+            true
+        } else {
+            limit.suppressed += 1;
+            false
+        }
+    }
+
+    /// Add the notes about log entries that were discarded by the rate limit to the frame, for the
+    /// callsites whose interval has passed at the provided moment, or for all callsites when no
+    /// moment is provided. Returns true if any notes were added to the frame.
+    fn write_notes(&mut self, now: Option<Instant>, result: &mut io::Result<()>) -> bool {
+        let mut written = false;
+
+        for note in self.expired_limits(now) {
+            written |= self.write_entry(&note, result);
+        }
+
+        written
+    }
+
+    /// Forget the callsites whose rate limiting interval has passed at the provided moment, or all
+    /// callsites when no moment is provided, and return notes about the entries they discarded.
+    fn expired_limits(&mut self, now: Option<Instant>) -> Vec<Note> {
+        let Some((_, interval)) = self.rate_limit else {
+            return Vec::new();
+        };
+
+        let mut notes = Vec::new();
+
+        self.limits.retain(|_, limit| {
+            let expired = now.is_none_or(|now| now.saturating_duration_since(limit.start) >= interval);
+
+            if expired {
+                notes.extend(limit.note());
+            }
+
+            !expired
+        });
+
+        notes
+    }
+
+    /// Add a log entry to the history, and to the pager if it is shown.
+    fn record(&mut self, entry: &[u8]) {
+        if self.history_capacity == 0 && self.pager.is_none() {
+            return;
+        }
+
+        let text = ansi::strip(&String::from_utf8_lossy(entry));
+        let text = text.strip_suffix('\n').unwrap_or(&text);
+
+        if let Some(pager) = &mut self.pager {
+            pager.push(text);
+        }
+
+        if self.history_capacity == 0 {
+            return;
+        }
+
+        if self.history.len() == self.history_capacity {
+            self.history.pop_front();
+        }
+
+        self.history.push_back(text.to_owned());
+    }
+
+    /// Add a log entry to the frame using the provided style. The style is reset before the
+    /// trailing newline, so that it does not bleed into the status area.
+    fn write_styled(&mut self, entry: &[u8], style: ContentStyle) {
+        let entry = String::from_utf8_lossy(entry);
+
+        let (line, newline) = match entry.strip_suffix('\n') {
+            Some(line) => (line, "\n"),
+            None => (&*entry, ""),
+        };
+
+        // Writing to a vector can not fail
+        let _ = write!(self.frame, "{}{newline}", style.apply(line));
+    }
+
+    /// Return what the terminal currently shows, so that it can be restored when the next frame
+    /// can not be written.
+    fn shown(&self) -> Shown {
+        Shown {
+            line_widths: self.line_widths.clone(),
+            region: self.region,
+            painted: self.painted.clone(),
+            repeat: self.repeat.clone(),
+        }
+    }
+
+    /// Write the frame like `present`, restoring the provided state of the terminal if that fails.
+    fn present_or_restore(&mut self, entries: bool, shown: Shown) -> io::Result<()> {
+        let result = self.present(entries);
+
+        if result.is_err() {
+            self.line_widths = shown.line_widths;
+            self.region = shown.region;
+            self.painted = shown.painted;
+            self.repeat = shown.repeat;
+        }
+
+        result
+    }
+
+    /// Write the frame to the output using a single write, and flush the output writer.
+    ///
+    /// When the frame contains log entries, raw mode is temporarily disabled if necessary. The
+    /// frame is cleared afterwards, even when writing failed.
+    fn present(&mut self, entries: bool) -> io::Result<()> {
+        // Disable raw mode if necessary
+        let raw_mode_guard = if entries && self.assume_raw_mode {
+            Some(RawModeGuard::new())
+        } else {
+            None
+        };
+
+        let result = self.write_frame();
+
+        // Re-enable raw mode if necessary
+        drop(raw_mode_guard);
+
+        self.frame.clear();
+
+        result
+    }
+
+    /// Write the frame to the output and flush it, retrying failed writes when the error policy
+    /// says so. A retry continues where the failed write left off.
+    fn write_frame(&mut self) -> io::Result<()> {
+        let (mut attempts, delay) = match self.error_policy {
+            ErrorPolicy::Retry { attempts, delay } => (attempts, delay),
+            _ => (0, Duration::ZERO),
+        };
+
+        let mut written = 0;
+
+        while written < self.frame.len() {
+            match self.output.write(&self.frame[written..]) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(count) => written += count,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => (),
+
+                Err(_) if attempts > 0 => {
+                    attempts -= 1;
+                    thread::sleep(delay);
+                }
+
+                Err(error) => return Err(error),
+            }
+        }
+
+        loop {
+            match self.output.flush() {
+                Err(_) if attempts > 0 => {
+                    attempts -= 1;
+                    thread::sleep(delay);
+                }
+
+                result => return result,
+            }
+        }
+    }
+
+    /// Record an error and handle it according to the error policy.
+    fn handle_error(&mut self, error: io::Error) {
+        self.health.record(&error);
+
+        match &self.error_policy {
+            ErrorPolicy::Ignore | ErrorPolicy::Retry { .. } => (),
+            ErrorPolicy::DisableStatus => self.disable_status(),
+            ErrorPolicy::Custom(handler) => handler(&error),
+        }
+    }
+
+    /// Stop drawing the status line, and record this in the health of the handler.
+    fn disable_status(&mut self) {
+        self.health.disable_status();
+        self.suspend();
+    }
+
+    /// Erase the status area, show the cursor, and fall back to plain log passthrough without
+    /// heartbeats.
+    ///
+    /// Raw mode is no longer toggled around log entries afterwards. The panic hook disables raw
+    /// mode for good, and re-enabling it after every log entry would leave the terminal in raw mode
+    /// when the program exits.
+    fn suspend(&mut self) {
+        self.heartbeat = None;
+        self.on_finish = FinishMode::Clear;
+        self.assume_raw_mode = false;
+
+        if !self.terminal {
+            return;
+        }
+
+        self.terminal = false;
+
+        // The output may be what failed, so errors are ignored here
+        self.frame.clear();
+
+        // Log entries that were held back by the pager are written as plain log lines
+        let held = !self.held.is_empty();
+
+        if self.pager.take().is_some() {
+            let _ = crossterm::queue!(self.frame, LeaveAlternateScreen);
+            self.frame.append(&mut self.held);
+        } else {
+            let _ = self.erase();
+        }
+
+        let _ = crossterm::queue!(self.frame, Show);
+        let _ = self.present(held);
+        self.region = None;
+    }
+
+    /// Add the commands that erase the status area, and reset the scroll region if there is one,
+    /// to the frame.
+    fn erase(&mut self) -> io::Result<()> {
+        if self.region.is_none() {
+            return self.clear();
+        }
+
+        crossterm::queue!(
+            self.frame,
+            MoveToColumn(0),
+            ResetColor,
+            Clear(ClearType::FromCursorDown),
+            SavePosition,
+            SetScrollRegion(None),
+            RestorePosition,
+        )
+    }
+
+    /// Set up a scroll region that leaves the provided number of rows at the bottom of the
+    /// terminal for the status area.
+    ///
+    /// The cursor is expected to be at the start of the line where the next log entry will be
+    /// written. Everything below it belongs to the previous status area, or is empty.
+    fn set_region(&mut self, size: (u16, u16), rows: u16) -> io::Result<()> {
+        // Reset the scroll region and erase the previous status area
+        crossterm::queue!(
+            self.frame,
+            SavePosition,
+            SetScrollRegion(None),
+            RestorePosition,
+            MoveToColumn(0),
+            ResetColor,
+            Clear(ClearType::FromCursorDown),
+        )?;
+
+        // Make room for the status area by scrolling the log messages up if necessary
+        self.frame.extend_from_slice("\n".repeat(rows.into()).as_bytes());
+
+        crossterm::queue!(
+            self.frame,
+            MoveUp(rows),
+            MoveToColumn(0),
+            SavePosition,
+            SetScrollRegion(Some((1, size.1 - rows))),
+            RestorePosition,
+        )?;
+
+        self.region = Some(Region {
+            size,
+            rows,
+        });
+
+        Ok(())
+    }
+
+    /// Paint the rendered status area in the rows below the scroll region, leaving the cursor
+    /// where it was.
+    fn paint(&mut self, size: (u16, u16), rows: u16) -> io::Result<()> {
+        crossterm::queue!(
+            self.frame,
+            SavePosition,
+            MoveTo(0, size.1 - rows),
+            ResetColor,
+            Clear(ClearType::FromCursorDown),
+        )?;
+
+        self.frame.extend_from_slice(self.status.as_bytes());
+
+        crossterm::queue!(
+            self.frame,
+            ResetColor,
+            RestorePosition,
+        )?;
+
+        self.painted.clear();
+        self.painted.extend_from_slice(self.status.as_bytes());
+
+        Ok(())
+    }
+
+    /// Return true when the status area should be pinned to the bottom of a terminal of the
+    /// provided size. The status area is drawn inline instead when the terminal is too small to fit
+    /// a row for log messages besides it.
+    fn pinned(&self, size: (u16, u16)) -> bool {
+        self.mode == RenderMode::ScrollRegion && size.1 > 1
+    }
+
+    /// Add a batch of log entries to the frame, to be written into the scroll region, and repaint
+    /// the pinned status area if it changed. The terminal must have room for at least one row of
+    /// log messages, see `pinned`.
+    fn write_pinned(&mut self, size: (u16, u16)) -> io::Result<()> {
+        self.render_status(size)?;
+
+        // Keep at least one row for log messages
+        let rows = physical_rows(&self.status.line_widths(), size.0)
+            .clamp(1, usize::from(size.1 - 1)) as u16;
+
+        let region = Region {
+            size,
+            rows,
+        };
+
+        if self.region != Some(region) {
+            self.set_region(size, rows)?;
+            self.paint(size, rows)?;
+        } else if self.status.as_bytes() != self.painted {
+            self.paint(size, rows)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a batch of log entries to the output, followed by a fresh status area.
+    ///
+    /// The previous status area is erased first. Entries are written in order, with raw mode
+    /// temporarily disabled if necessary. An empty batch simply redraws the status area.
+    ///
+    /// The complete frame is written to the output using a single write. When synchronized output
+    /// is enabled the frame is wrapped in a synchronized update, so that the terminal shows it all
+    /// at once.
+    ///
+    /// When the output is not a terminal the entries are written without a status area, followed
+    /// by a heartbeat if one is due.
+    pub fn write<I>(&mut self, entries: I)
+    where
+        I: IntoIterator,
+        I::Item: Entry,
+    {
+        if let Err(error) = self.try_write(entries) {
+            self.handle_error(error);
+        }
+    }
+
+    /// Write a batch of log entries, returning any error that occurred.
+    ///
+    /// The log entries are written even when the status line callback fails.
+    fn try_write<I>(&mut self, entries: I) -> io::Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Entry,
+    {
+        self.frame.clear();
+
+        // Hold back log entries while the pager is shown, and show them in the pager instead
+        if self.pager.is_some() {
+            let (entries, routed) = self.write_entries(entries);
+            self.held.append(&mut self.frame);
+
+            let pager = match entries {
+                true => self.draw_pager(),
+                false => Ok(()),
+            };
+
+            return routed.and(pager);
+        }
+
+        if !self.terminal {
+            let (entries, routed) = self.write_entries(entries);
+            let status = self.heartbeat();
+
+            self.present(entries)?;
+            return routed.and(status);
+        }
+
+        if self.synchronized {
+            crossterm::queue!(
+                self.frame,
+                BeginSynchronizedUpdate,
+            )?;
+        }
+
+        let start = self.frame.len();
+        let size = self.size();
+
+        // The frame updates what the terminal shows, which only applies once it was written
+        let shown = self.shown();
+
+        // Errors of the status line callback are reported after the frame was written, so that no
+        // log entries are lost
+        let (entries, routed, status) = match self.pinned(size) {
+            false => {
+                // A pinned status area is drawn inline while the terminal is too small for it
+                self.erase()?;
+                self.region = None;
+
+                let (entries, routed) = self.write_entries(entries);
+
+                (entries, routed, self.draw())
+            }
+
+            true => {
+                // An inline status area, e.g. from when the terminal was too small, is erased
+                // before the status area is pinned again
+                if self.region.is_none() {
+                    self.clear()?;
+                }
+
+                let (entries, routed) = self.write_entries(entries);
+
+                (entries, routed, self.write_pinned(size))
+            }
+        };
+
+        // Skip the write when there is nothing to show
+        if self.frame.len() == start {
+            self.frame.clear();
+            return routed.and(status);
+        }
+
+        if self.synchronized {
+            crossterm::queue!(
+                self.frame,
+                EndSynchronizedUpdate,
+            )?;
+        }
+
+        self.present_or_restore(entries, shown)?;
+
+        routed.and(status)
+    }
+
+    /// Erase the status area and write the provided panic message as a plain log line. The status
+    /// line is not drawn anymore afterwards.
+    pub fn panic(&mut self, message: &[u8]) {
+        self.suspend();
+        self.write([message]);
+    }
+
+    /// Finalize the output when the log handler shuts down.
+    ///
+    /// Depending on the finish mode the status area is erased, drawn one last time, or replaced
+    /// by a summary. A final status area is followed by a newline, so that any output written
+    /// after the handler stopped appears below it. A pinned status area is moved below the last log
+    /// message and the scroll region is reset. When the output is not a terminal the final status
+    /// is printed as a heartbeat or a summary line.
+    ///
+    /// The status line is not drawn anymore afterwards.
+    pub fn finish(&mut self) {
+        if let Err(error) = self.try_finish() {
+            self.handle_error(error);
+        }
+
+        self.terminal = false;
+        self.heartbeat = None;
+        self.on_finish = FinishMode::Clear;
+    }
+
+    /// Finalize the output, returning any error that occurred.
+    fn try_finish(&mut self) -> io::Result<()> {
+        self.close_pager()?;
+        self.frame.clear();
+
+        // Notifications are never left behind in the final status area
+        self.toasts.clear();
+
+        // Pending notes about log entries that were discarded by the rate limit are written first
+        let mut routed = Ok(());
+
+        if !self.terminal {
+            let entries = self.write_notes(None, &mut routed);
+
+            let status = match self.on_finish.clone() {
+                FinishMode::Clear => Ok(()),
+
+                FinishMode::Keep => {
+                    self.next_heartbeat = Instant::now();
+                    self.heartbeat()
+                }
+
+                FinishMode::Summary(summary) => {
+                    let (columns, rows) = self.size();
+                    self.status.reset(columns, rows);
+
+                    summary(&mut self.status).map(|()| {
+                        let text = self.plain_status();
+                        self.frame.extend_from_slice(text.as_bytes());
+                    })
+                }
+            };
+
+            self.present(entries)?;
+            return routed.and(status);
+        }
+
+        if self.synchronized {
+            crossterm::queue!(
+                self.frame,
+                BeginSynchronizedUpdate,
+            )?;
+        }
+
+        self.erase()?;
+        self.region = None;
+
+        let entries = self.write_notes(None, &mut routed);
+
+        let status = match self.on_finish.clone() {
+            FinishMode::Clear => Ok(false),
+            FinishMode::Keep => self.draw().map(|()| true),
+
+            FinishMode::Summary(summary) => {
+                let (columns, rows) = self.size();
+                self.status.reset(columns, rows);
+
+                summary(&mut self.status)
+                    .and_then(|()| self.place_status())
+                    .map(|()| true)
+            }
+        };
+
+        // End the final status area with a newline
+        if let Ok(true) = status {
+            if !self.status.as_bytes().is_empty() {
+                crossterm::queue!(
+                    self.frame,
+                    ResetColor,
+                )?;
+
+                self.frame.push(b'\n');
+            }
+        }
+
+        self.line_widths.clear();
+
+        if self.synchronized {
+            crossterm::queue!(
+                self.frame,
+                EndSynchronizedUpdate,
+            )?;
+        }
+
+        self.present(entries)?;
+
+        routed.and(status.map(|_| ()))
+    }
+
+    /// Draw the pager on the alternate screen, if it is shown.
+    fn draw_pager(&mut self) -> io::Result<()> {
+        let size = self.size();
+
+        let Some(pager) = &mut self.pager else {
+            return Ok(());
+        };
+
+        self.frame.clear();
+
+        if self.synchronized {
+            crossterm::queue!(
+                self.frame,
+                BeginSynchronizedUpdate,
+            )?;
+        }
+
+        pager.render(&mut self.frame, size)?;
+
+        if self.synchronized {
+            crossterm::queue!(
+                self.frame,
+                EndSynchronizedUpdate,
+            )?;
+        }
+
+        self.present(false)
+    }
+
+    /// Erase the status area, switch to the alternate screen, and show the pager. Nothing happens
+    /// when the output is not a terminal or the pager is already shown.
+    fn open_pager(&mut self) -> io::Result<()> {
+        if !self.terminal || self.pager.is_some() {
+            return Ok(());
+        }
+
+        self.frame.clear();
+        self.erase()?;
+
+        crossterm::queue!(
+            self.frame,
+            EnterAlternateScreen,
+        )?;
+
+        // The scroll region was reset by erasing the status area
+        self.region = None;
+        self.present(false)?;
+
+        self.pager = Some(Pager::new(self.history.iter().map(String::as_str)));
+        self.draw_pager()
+    }
+
+    /// Switch back to the main screen, write the log entries that arrived while the pager was
+    /// shown, and draw the status area again. Nothing happens when the pager is not shown.
+    fn close_pager(&mut self) -> io::Result<()> {
+        if self.pager.take().is_none() {
+            return Ok(());
+        }
+
+        self.frame.clear();
+
+        let shown = self.shown();
+
+        crossterm::queue!(
+            self.frame,
+            LeaveAlternateScreen,
+            Show,
+        )?;
+
+        let entries = !self.held.is_empty();
+        self.frame.append(&mut self.held);
+
+        // Errors of the status line callback are reported after the frame was written, so that no
+        // log entries are lost
+        let size = self.size();
+
+        let status = match self.pinned(size) {
+            false => self.draw(),
+            true => self.write_pinned(size),
+        };
+
+        self.present_or_restore(entries, shown)?;
+
+        status
+    }
+
+    /// Handle a command for the history pager.
+    ///
+    /// While the pager is shown, log entries are added to the history and the pager, but they are
+    /// not written to the output until the pager is closed.
+    pub fn pager(&mut self, command: PagerCommand) {
+        let result = match command {
+            PagerCommand::Open => self.open_pager(),
+            PagerCommand::Redraw => self.draw_pager(),
+            PagerCommand::Close => self.close_pager(),
+
+            PagerCommand::Key(key) => {
+                let (_, rows) = self.size();
+
+                if let Some(pager) = &mut self.pager {
+                    pager.key(key, rows);
+                }
+
+                self.draw_pager()
+            }
+        };
+
+        if let Err(error) = result {
+            self.handle_error(error);
+        }
+    }
+
+    /// Show a notification below the status lines until it expires. The status area is not redrawn
+    /// until the next batch of log entries is written. Nothing happens when the output is not a
+    /// terminal.
+    pub fn notify(&mut self, toast: Toast) {
+        if self.terminal {
+            self.toasts.push(toast);
+        }
+    }
+
+    /// Return the moment at which the status area should be redrawn when no log entries arrive,
+    /// which is the earlier of the provided refresh deadline and the expiry of the first
+    /// notification to disappear.
+    pub fn deadline(&self, refresh: Option<Instant>) -> Option<Instant> {
+        self.toasts
+            .iter()
+            .map(|toast| toast.expires)
+            .chain(refresh)
+            .min()
+    }
+
+    /// Redraw the status area without writing any log entries.
+    pub fn redraw(&mut self) {
+        self.write(std::iter::empty::<&[u8]>())
+    }
+
+    /// Flush the output writer.
+    pub fn flush(&mut self) {
+        if let Err(error) = self.output.flush() {
+            self.handle_error(error);
+        }
+    }
+}
+
+/// A `Drop` impl that closes the pager, and erases the pinned status area and resets the scroll
+/// region.
+impl<T, W> Drop for Renderer<T, W>
+where
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
+    W: Write,
+{
+    fn drop(&mut self) {
+        // Errors can not be reported from a drop impl
+        if self.pager.is_some() {
+            let _ = self.close_pager();
+        }
+
+        if self.region.is_none() {
+            return;
+        }
+
+        self.frame.clear();
+        let _ = self.erase();
+        let _ = self.present(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::testing::VirtualTerminal;
+
+    /// A writer that fails a configurable number of writes before passing them on to a virtual
+    /// terminal.
+    #[derive(Clone)]
+    struct FailingWriter {
+        /// The terminal that successful writes are passed on to.
+        terminal: VirtualTerminal,
+
+        /// The number of writes that fail before writes succeed again.
+        failures: Arc<AtomicU32>,
+
+        /// The number of write calls, including the failed ones.
+        writes: Arc<AtomicU32>,
+    }
+
+    impl FailingWriter {
+        /// Create a writer for a virtual terminal of the provided size.
+        fn new(columns: u16, rows: u16) -> Self {
+            Self {
+                terminal: VirtualTerminal::new(columns, rows),
+                failures: Arc::default(),
+                writes: Arc::default(),
+            }
+        }
+
+        /// Fail the next `count` writes.
+        fn fail(&self, count: u32) {
+            self.failures.store(count, Ordering::Relaxed);
+        }
+
+        /// Return the number of write calls so far, and reset the count.
+        fn take_writes(&self) -> u32 {
+            self.writes.swap(0, Ordering::Relaxed)
+        }
+    }
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.writes.fetch_add(1, Ordering::Relaxed);
+
+            let failing = self.failures
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |failures| failures.checked_sub(1))
+                .is_ok();
+
+            match failing {
+                true => Err(io::ErrorKind::BrokenPipe.into()),
+                false => self.terminal.write(buf),
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Return settings for a forced 20x5 terminal that uses the provided error policy.
+    fn error_settings(policy: ErrorPolicy) -> Settings {
+        Settings {
+            terminal_size: Some((20, 5)),
+            force_terminal: Some(true),
+            synchronized_output: SynchronizedOutput::Disabled,
+            error_policy: policy,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn lines_that_fill_the_terminal_do_not_wrap() {
+        assert_eq!(physical_rows(&[0, 10, 11, 25], 10), 1 + 1 + 2 + 3);
+    }
+
+    #[test]
+    fn scroll_region_pins_the_status_area() {
+        let terminal = VirtualTerminal::new(20, 5);
+
+        let settings = Settings {
+            terminal_size: Some((20, 5)),
+            force_terminal: Some(true),
+            render_mode: RenderMode::ScrollRegion,
+            synchronized_output: SynchronizedOutput::Disabled,
+            ..Settings::default()
+        };
+
+        let mut renderer = Renderer::new(|output: &mut StatusWriter| write!(output, "status"), terminal.clone(), &settings);
+
+        renderer.write(["first\n", "second\n"]);
+
+        assert_eq!(terminal.scroll_region(), Some((0, 3)));
+        assert_eq!(terminal.screen(), ["first", "second", "", "", "status"]);
+        assert_eq!(terminal.cursor(), (0, 2));
+
+        drop(renderer);
+
+        assert_eq!(terminal.scroll_region(), None);
+        assert_eq!(terminal.screen(), ["first", "second", "", "", ""]);
+    }
+
+    #[test]
+    fn scroll_region_falls_back_to_inline_without_room_for_log_messages() {
+        let terminal = VirtualTerminal::new(20, 1);
+
+        let settings = Settings {
+            terminal_size: Some((20, 1)),
+            force_terminal: Some(true),
+            render_mode: RenderMode::ScrollRegion,
+            synchronized_output: SynchronizedOutput::Disabled,
+            ..Settings::default()
+        };
+
+        let mut renderer = Renderer::new(|output: &mut StatusWriter| write!(output, "status"), terminal.clone(), &settings);
+
+        renderer.write(["first\n"]);
+
+        assert_eq!(terminal.scroll_region(), None);
+        assert_eq!(terminal.scrollback(), ["first"]);
+        assert_eq!(terminal.screen(), ["status"]);
+
+        // The status area is pinned again once the terminal is large enough
+        terminal.resize(20, 4);
+        renderer.terminal_size = Some((20, 4));
+        renderer.write(["second\n"]);
+
+        assert_eq!(terminal.scroll_region(), Some((0, 2)));
+        assert_eq!(terminal.screen(), ["second", "", "", "status"]);
+    }
+
+    #[test]
+    fn repeats_are_compared_without_timestamps() {
+        assert_eq!(repeat_key(b"2024-05-01T12:00:00.123456Z  INFO app: retrying\n"), "INFO app: retrying\n");
+        assert_eq!(repeat_key(b"\x1b[2m2024-05-01 12:00:00\x1b[0m WARN retrying\n"), "WARN retrying\n");
+        assert_eq!(repeat_key(b"   0.002513s  INFO retrying\n"), "INFO retrying\n");
+
+        // Numbers at the start of the message are kept
+        assert_eq!(repeat_key(b"3 files left\n"), "3 files left\n");
+    }
+
+    #[test]
+    fn panics_stop_toggling_raw_mode() {
+        let terminal = VirtualTerminal::new(40, 5);
+
+        let settings = Settings {
+            terminal_size: Some((40, 5)),
+            force_terminal: Some(true),
+            assume_raw_mode: true,
+            synchronized_output: SynchronizedOutput::Disabled,
+            ..Settings::default()
+        };
+
+        let mut renderer = Renderer::new(|output: &mut StatusWriter| write!(output, "status"), terminal.clone(), &settings);
+
+        renderer.panic(b"thread 'main' panicked\n");
+
+        // Log entries written while unwinding must not enable raw mode again
+        assert!(!renderer.assume_raw_mode);
+
+        renderer.write(["after the panic\n"]);
+
+        assert_eq!(terminal.contents(), "thread 'main' panicked\nafter the panic");
+        assert!(terminal.cursor_visible());
+    }
+
+    #[test]
+    fn status_area_is_limited_to_the_terminal_height() {
+        let terminal = VirtualTerminal::new(20, 5);
+
+        let settings = Settings {
+            terminal_size: Some((20, 5)),
+            force_terminal: Some(true),
+            synchronized_output: SynchronizedOutput::Disabled,
+            ..Settings::default()
+        };
+
+        let status = |output: &mut StatusWriter| write!(output, "1\n2\n3\n4\n5\n6");
+        let mut renderer = Renderer::new(status, terminal.clone(), &settings);
+
+        renderer.write(["first\n"]);
+        renderer.write(["second\n"]);
+
+        // The status area is erased completely before every entry, so only log entries scroll out
+        assert_eq!(terminal.scrollback(), ["first"]);
+        assert_eq!(terminal.screen(), ["second", "1", "2", "3", "+3 more"]);
+
+        // A lower limit is applied on top of the terminal height
+        let settings = Settings {
+            max_status_lines: Some(2),
+            ..settings
+        };
+
+        let terminal = VirtualTerminal::new(20, 5);
+        let mut renderer = Renderer::new(status, terminal.clone(), &settings);
+
+        renderer.write(["first\n"]);
+
+        assert_eq!(terminal.screen(), ["first", "1", "+5 more", "", ""]);
+    }
+
+    #[test]
+    fn first_status_line_is_kept_when_limiting_the_status_area() {
+        let settings = Settings {
+            terminal_size: Some((10, 5)),
+            force_terminal: Some(true),
+            synchronized_output: SynchronizedOutput::Disabled,
+            max_status_lines: Some(1),
+            ..Settings::default()
+        };
+
+        // A single row only fits the first line, without the indicator
+        let terminal = VirtualTerminal::new(10, 5);
+        let mut renderer = Renderer::new(|output: &mut StatusWriter| write!(output, "one\ntwo"), terminal.clone(), &settings);
+
+        renderer.write(["first\n"]);
+
+        assert_eq!(terminal.screen(), ["first", "one", "", "", ""]);
+
+        // A line that is wider than the status area is trimmed to the rows it may take up
+        let terminal = VirtualTerminal::new(10, 5);
+        let mut renderer = Renderer::new(|output: &mut StatusWriter| write!(output, "{}", "x".repeat(25)), terminal.clone(), &settings);
+
+        renderer.write(["first\n"]);
+
+        assert_eq!(terminal.screen(), ["first", "xxxxxxxxx…", "", "", ""]);
+
+        // With more rows the trimmed first line is followed by the indicator
+        let settings = Settings {
+            max_status_lines: Some(2),
+            ..settings
+        };
+
+        let terminal = VirtualTerminal::new(10, 5);
+        let mut renderer = Renderer::new(|output: &mut StatusWriter| write!(output, "{}\ntwo", "x".repeat(25)), terminal.clone(), &settings);
+
+        renderer.write(["first\n"]);
+
+        assert_eq!(terminal.screen(), ["first", "xxxxxxxxx…", "+1 more", "", ""]);
+    }
+
+    #[test]
+    fn failed_writes_are_retried() {
+        let writer = FailingWriter::new(20, 5);
+
+        let settings = error_settings(ErrorPolicy::Retry {
+            attempts: 2,
+            delay: Duration::ZERO,
+        });
+
+        let health = settings.health.clone();
+        let mut renderer = Renderer::new(|output: &mut StatusWriter| write!(output, "status"), writer.clone(), &settings);
+
+        // Two failures are resolved by retrying, and are not counted as errors
+        writer.fail(2);
+        renderer.write(["first\n"]);
+
+        assert_eq!(writer.take_writes(), 3);
+        assert_eq!(writer.terminal.contents(), "first\nstatus");
+        assert!(health.snapshot().is_healthy());
+
+        // The error is recorded once all attempts failed, and the status line is kept
+        writer.fail(3);
+        renderer.write(["lost\n"]);
+
+        assert_eq!(writer.take_writes(), 3);
+        assert_eq!(health.snapshot().errors(), 1);
+        assert_eq!(health.snapshot().last_error(), Some(io::ErrorKind::BrokenPipe));
+        assert!(!health.snapshot().status_disabled());
+
+        renderer.write(["second\n"]);
+
+        assert_eq!(writer.terminal.contents(), "first\nsecond\nstatus");
+    }
+
+    #[test]
+    fn failed_writes_keep_the_previous_status_area() {
+        let writer = FailingWriter::new(20, 5);
+        let settings = error_settings(ErrorPolicy::Ignore);
+        let lines = Arc::new(AtomicU32::new(1));
+
+        let status = {
+            let lines = lines.clone();
+
+            move |output: &mut StatusWriter| {
+                for line in 1..=lines.load(Ordering::Relaxed) {
+                    write!(output, "{}status {line}", if line > 1 { "\n" } else { "" })?;
+                }
+
+                Ok(())
+            }
+        };
+
+        let mut renderer = Renderer::new(status, writer.clone(), &settings);
+
+        renderer.write(["first\n"]);
+
+        // The taller status area is never shown, so it must not be erased either
+        lines.store(3, Ordering::Relaxed);
+        writer.fail(1);
+        renderer.write(["lost\n"]);
+
+        lines.store(1, Ordering::Relaxed);
+        renderer.write(["second\n"]);
+
+        assert_eq!(writer.terminal.contents(), "first\nsecond\nstatus 1");
+    }
+
+    #[test]
+    fn failed_writes_disable_the_status_area() {
+        let writer = FailingWriter::new(20, 5);
+        let settings = error_settings(ErrorPolicy::DisableStatus);
+        let health = settings.health.clone();
+        let mut renderer = Renderer::new(|output: &mut StatusWriter| write!(output, "status"), writer.clone(), &settings);
+
+        renderer.write(["first\n"]);
+        writer.fail(1);
+        renderer.write(["lost\n"]);
+
+        assert_eq!(health.snapshot().errors(), 1);
+        assert_eq!(health.snapshot().last_error(), Some(io::ErrorKind::BrokenPipe));
+        assert!(health.snapshot().status_disabled());
+
+        // The status area was erased, and log entries pass through without it
+        assert_eq!(writer.terminal.contents(), "first");
+
+        renderer.write(["second\n", "third\n"]);
+
+        assert_eq!(writer.terminal.contents(), "first\nsecond\nthird");
+        assert_eq!(writer.terminal.cursor(), (0, 3));
+        assert!(writer.terminal.cursor_visible());
+    }
+
+    #[test]
+    fn failing_callbacks_disable_the_status_area() {
+        let terminal = VirtualTerminal::new(20, 5);
+        let settings = error_settings(ErrorPolicy::DisableStatus);
+        let health = settings.health.clone();
+
+        let status = |_: &mut StatusWriter| Err(io::Error::other("callback failed"));
+        let mut renderer = Renderer::new(status, terminal.clone(), &settings);
+
+        renderer.write(["first\n"]);
+        renderer.write(["second\n"]);
+
+        assert_eq!(terminal.contents(), "first\nsecond");
+        assert_eq!(health.snapshot().errors(), 1);
+        assert_eq!(health.snapshot().last_error(), Some(io::ErrorKind::Other));
+        assert!(health.snapshot().status_disabled());
+    }
+
+    #[test]
+    fn custom_policies_receive_errors() {
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let received = errors.clone();
+
+        let writer = FailingWriter::new(20, 5);
+
+        let settings = error_settings(ErrorPolicy::custom(move |error| {
+            received.lock().unwrap().push(error.kind());
+        }));
+
+        let health = settings.health.clone();
+        let mut renderer = Renderer::new(|output: &mut StatusWriter| write!(output, "status"), writer.clone(), &settings);
+
+        writer.fail(1);
+        renderer.write(["lost\n"]);
+        renderer.write(["first\n"]);
+
+        assert_eq!(*errors.lock().unwrap(), [io::ErrorKind::BrokenPipe]);
+        assert_eq!(writer.take_writes(), 2);
+        assert_eq!(writer.terminal.contents(), "first\nstatus");
+
+        // Only `DisableStatus` disables the status line
+        assert_eq!(health.snapshot().errors(), 1);
+        assert!(!health.snapshot().status_disabled());
+    }
+}
@@ -0,0 +1,66 @@
+use std::thread::JoinHandle;
+
+use crate::StatusHandle;
+
+/// A background thread that redraws the status area whenever the terminal is resized.
+///
+/// Redrawing right after a resize erases the previous status area using the new terminal width, so
+/// that status lines that were rewrapped by the terminal are cleaned up completely. Resize events
+/// are only delivered on Unix platforms, on other platforms the watcher does nothing and the
+/// status area is measured again on the next redraw.
+///
+/// The thread is stopped and joined when the watcher is dropped.
+pub(crate) struct ResizeWatcher {
+    /// A handle to the registered signal iterator, used to stop the thread.
+    #[cfg(unix)]
+    signals: signal_hook::iterator::Handle,
+
+    /// A join handle that represents the watcher thread.
+    join_handle: Option<JoinHandle<()>>,
+}
+
+impl ResizeWatcher {
+    /// Spawn a new watcher thread that redraws the status area through the provided handle.
+    ///
+    /// Returns `None` when the resize signal could not be registered.
+    #[cfg(unix)]
+    pub fn new(handle: StatusHandle) -> Option<Self> {
+        use signal_hook::consts::SIGWINCH;
+        use signal_hook::iterator::Signals;
+
+        let mut signals = Signals::new([SIGWINCH]).ok()?;
+        let signals_handle = signals.handle();
+
+        let join_handle = std::thread::spawn(move || {
+            for _ in signals.forever() {
+                // Errors are not fatal here, the next log message will report them
+                let _ = handle.redraw();
+            }
+        });
+
+        Some(Self {
+            signals: signals_handle,
+            join_handle: Some(join_handle),
+        })
+    }
+
+    /// Resize events are not supported on this platform, so no watcher is created.
+    #[cfg(not(unix))]
+    pub fn new(_handle: StatusHandle) -> Option<Self> {
+        None
+    }
+}
+
+/// A `Drop` impl that shuts down and joins the watcher thread.
+impl Drop for ResizeWatcher {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        self.signals.close();
+
+        // The watcher thread invokes the status line callback, which may have paniced. That is not
+        // reported again here, because the handler may be dropped while unwinding from that panic.
+        if let Some(join_handle) = self.join_handle.take() {
+            let _ = join_handle.join();
+        }
+    }
+}
@@ -0,0 +1,420 @@
+use std::fmt::{self, Debug, Display};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+
+use tracing::{Subscriber, span};
+use tracing::field::{Field, Visit};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+/// The field values recorded for a span. This is stored in the span's extensions.
+#[derive(Clone, Default)]
+struct SpanFields(Vec<(&'static str, String)>);
+
+impl SpanFields {
+    /// Insert or replace the value of a field.
+    fn set(&mut self, name: &'static str, value: String) {
+        match self.0.iter_mut().find(|(field, _)| *field == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.0.push((name, value)),
+        }
+    }
+}
+
+impl Visit for SpanFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field.name(), value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.set(field.name(), format!("{value:?}"));
+    }
+}
+
+/// A span that is currently entered on some thread.
+#[derive(Clone, Debug)]
+pub struct ActiveSpan {
+    /// The span's ID, used to apply recorded field values.
+    id: span::Id,
+
+    /// The name of the span.
+    name: &'static str,
+
+    /// The target of the span.
+    target: &'static str,
+
+    /// The recorded field values of the span.
+    fields: Vec<(&'static str, String)>,
+}
+
+impl ActiveSpan {
+    /// Return the name of the span.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Return the target of the span.
+    pub fn target(&self) -> &'static str {
+        self.target
+    }
+
+    /// Return the recorded fields of the span as name/value pairs, in the order in which they
+    /// were first recorded.
+    pub fn fields(&self) -> &[(&'static str, String)] {
+        &self.fields
+    }
+
+    /// Return the value of the field with the given name, if it was recorded.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Formats the span as its name followed by its field values, e.g. `compiling foo`.
+impl Display for ActiveSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)?;
+
+        for (_, value) in &self.fields {
+            write!(f, " {value}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The stack of spans that are currently entered on a thread, outermost span first.
+#[derive(Clone, Debug)]
+pub struct SpanStack {
+    /// The thread on which the spans are entered.
+    thread: ThreadId,
+
+    /// The name of the thread, if it has one.
+    thread_name: Option<String>,
+
+    /// The entered spans, outermost first.
+    spans: Vec<ActiveSpan>,
+}
+
+impl SpanStack {
+    /// Return the ID of the thread on which the spans are entered.
+    pub fn thread(&self) -> ThreadId {
+        self.thread
+    }
+
+    /// Return the name of the thread on which the spans are entered, if it has one.
+    pub fn thread_name(&self) -> Option<&str> {
+        self.thread_name.as_deref()
+    }
+
+    /// Return the entered spans, outermost first.
+    pub fn spans(&self) -> &[ActiveSpan] {
+        &self.spans
+    }
+
+    /// Return the innermost entered span.
+    pub fn current(&self) -> Option<&ActiveSpan> {
+        self.spans.last()
+    }
+}
+
+/// Formats the stack as a breadcrumb trail, e.g. `compiling foo > codegen`.
+impl Display for SpanStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, span) in self.spans.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" > ")?;
+            }
+
+            write!(f, "{span}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The shared state behind `ActiveSpans`.
+#[derive(Default)]
+struct Stacks {
+    /// The span stacks of all threads that currently have spans entered, in the order in which
+    /// the threads first entered a span.
+    stacks: Vec<SpanStack>,
+
+    /// The thread whose stack was most recently updated.
+    most_recent: Option<ThreadId>,
+}
+
+/// A snapshot source for the spans that are currently entered on each thread.
+///
+/// Instances are obtained from `StatusLayer::spans` and are cheap to clone, so that they can be
+/// moved into the state of a status line callback.
+#[derive(Clone, Default)]
+pub struct ActiveSpans(Arc<Mutex<Stacks>>);
+
+impl ActiveSpans {
+    /// Lock the shared state.
+    fn lock(&self) -> MutexGuard<'_, Stacks> {
+        self.0.lock().expect("Active spans mutex was poisoned")
+    }
+
+    /// Return the span stacks of all threads that currently have spans entered.
+    pub fn snapshot(&self) -> Vec<SpanStack> {
+        self.lock().stacks.clone()
+    }
+
+    /// Return the span stack of the thread that most recently entered or exited a span, if it
+    /// still has spans entered.
+    pub fn most_recent(&self) -> Option<SpanStack> {
+        let stacks = self.lock();
+        let thread = stacks.most_recent?;
+
+        stacks.stacks
+            .iter()
+            .find(|stack| stack.thread == thread)
+            .cloned()
+    }
+
+    /// Push a span onto the stack of the current thread.
+    fn enter(&self, span: ActiveSpan) {
+        let current = thread::current();
+        let mut stacks = self.lock();
+
+        let index = match stacks.stacks.iter().position(|stack| stack.thread == current.id()) {
+            Some(index) => index,
+
+            None => {
+                stacks.stacks.push(SpanStack {
+                    thread: current.id(),
+                    thread_name: current.name().map(str::to_owned),
+                    spans: Vec::new(),
+                });
+
+                stacks.stacks.len() - 1
+            }
+        };
+
+        stacks.stacks[index].spans.push(span);
+        stacks.most_recent = Some(current.id());
+    }
+
+    /// Remove the innermost occurrence of a span from the stack of the current thread.
+    fn exit(&self, id: &span::Id) {
+        let current = thread::current().id();
+        let mut stacks = self.lock();
+
+        let Some(index) = stacks.stacks.iter().position(|stack| stack.thread == current) else {
+            return;
+        };
+
+        let spans = &mut stacks.stacks[index].spans;
+
+        if let Some(position) = spans.iter().rposition(|span| span.id == *id) {
+            spans.remove(position);
+        }
+
+        if spans.is_empty() {
+            stacks.stacks.remove(index);
+        }
+
+        stacks.most_recent = Some(current);
+    }
+
+    /// Update the fields of all entered occurrences of a span.
+    fn record(&self, id: &span::Id, fields: &SpanFields) {
+        let mut stacks = self.lock();
+
+        for span in stacks.stacks.iter_mut().flat_map(|stack| stack.spans.iter_mut()) {
+            if span.id == *id {
+                span.fields.clone_from(&fields.0);
+            }
+        }
+    }
+}
+
+/// A `tracing_subscriber` layer that tracks which spans are entered on each thread.
+///
+/// The status line handlers only see formatted log output, so they have no knowledge of the span
+/// structure of the program. This layer fills that gap: it records the names and field values of
+/// all entered spans, and shares them through an `ActiveSpans` instance that can be read from a
+/// status line callback, e.g. to show `compiling foo > codegen`.
+///
+/// The layer should be added to a `tracing_subscriber::Registry` alongside the formatting layer
+/// that writes to the status line handler.
+#[derive(Default)]
+pub struct StatusLayer {
+    /// The shared span state.
+    spans: ActiveSpans,
+}
+
+impl StatusLayer {
+    /// Create a new layer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the shared span state that is updated by this layer.
+    pub fn spans(&self) -> ActiveSpans {
+        self.spans.clone()
+    }
+}
+
+impl<S> Layer<S> for StatusLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut fields = SpanFields::default();
+        attrs.record(&mut fields);
+
+        span.extensions_mut().insert(fields);
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut extensions = span.extensions_mut();
+
+        if let Some(fields) = extensions.get_mut::<SpanFields>() {
+            values.record(fields);
+            self.spans.record(id, fields);
+        }
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let fields = span.extensions()
+            .get::<SpanFields>()
+            .cloned()
+            .unwrap_or_default();
+
+        self.spans.enter(ActiveSpan {
+            id: id.clone(),
+            name: span.name(),
+            target: span.metadata().target(),
+            fields: fields.0,
+        });
+    }
+
+    fn on_exit(&self, id: &span::Id, _ctx: Context<'_, S>) {
+        self.spans.exit(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use tracing::info_span;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    /// Format the span stacks of a snapshot as breadcrumb trails.
+    fn trails(spans: &ActiveSpans) -> Vec<String> {
+        spans.snapshot()
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn spans_are_stacked_in_the_order_they_are_entered() {
+        let layer = StatusLayer::new();
+        let spans = layer.spans();
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let compile = info_span!("compiling", krate = "foo");
+            let codegen = info_span!("codegen", unit = 3);
+
+            let outer = compile.enter();
+
+            assert_eq!(trails(&spans), ["compiling foo"]);
+
+            let inner = codegen.enter();
+            codegen.record("unit", 4);
+
+            assert_eq!(trails(&spans), ["compiling foo > codegen 4"]);
+            assert_eq!(spans.most_recent().unwrap().current().unwrap().field("unit"), Some("4"));
+
+            // Exiting out of order removes the exited span only
+            drop(outer);
+
+            assert_eq!(trails(&spans), ["codegen 4"]);
+
+            drop(inner);
+
+            assert!(spans.snapshot().is_empty());
+            assert!(spans.most_recent().is_none());
+        });
+    }
+
+    #[test]
+    fn closed_spans_are_removed() {
+        let layer = StatusLayer::new();
+        let spans = layer.spans();
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = info_span!("download");
+            let entered = span.entered();
+
+            assert_eq!(trails(&spans), ["download"]);
+
+            drop(entered);
+        });
+
+        assert!(spans.snapshot().is_empty());
+    }
+
+    #[test]
+    fn every_thread_has_its_own_stack() {
+        let layer = StatusLayer::new();
+        let spans = layer.spans();
+        let subscriber = tracing_subscriber::registry().with(layer);
+        let dispatch = tracing::Dispatch::new(subscriber);
+
+        tracing::dispatcher::with_default(&dispatch, || {
+            let _main = info_span!("main").entered();
+
+            let (entered_sender, entered) = mpsc::channel();
+            let (exit, exit_receiver) = mpsc::channel();
+
+            thread::scope(|scope| {
+                let dispatch = &dispatch;
+
+                scope.spawn(move || {
+                    tracing::dispatcher::with_default(dispatch, || {
+                        let _worker = info_span!("worker", id = 1).entered();
+
+                        entered_sender.send(()).unwrap();
+                        exit_receiver.recv().unwrap();
+                    });
+                });
+
+                entered.recv().unwrap();
+
+                assert_eq!(trails(&spans), ["main", "worker 1"]);
+                assert_eq!(spans.most_recent().unwrap().to_string(), "worker 1");
+
+                exit.send(()).unwrap();
+            });
+
+            assert_eq!(trails(&spans), ["main"]);
+            assert!(spans.most_recent().is_none());
+        });
+    }
+}
@@ -0,0 +1,189 @@
+use std::io::{self, Write};
+
+use crossterm::style::{Attribute, ResetColor, SetAttribute};
+use tracing::Level;
+
+use crate::ansi::{self, Token};
+
+/// The writer that status line callbacks write their output to.
+///
+/// Status lines are not written to the output directly. Instead they are collected in this
+/// writer, so that the log handler can measure how many terminal rows the status area occupies.
+/// This is what allows the previous status area to be erased reliably, even when lines wrap or the
+/// terminal is resized.
+///
+/// The writer also exposes the size of the terminal, which is useful for status lines that adapt
+/// to the available width.
+pub struct StatusWriter {
+    /// The buffered status line output.
+    buffer: Vec<u8>,
+
+    /// The number of columns of the terminal.
+    columns: u16,
+
+    /// The number of rows of the terminal.
+    rows: u16,
+
+    /// The level of the most severe log entry in the batch that preceded the status line.
+    severity: Option<Level>,
+}
+
+impl StatusWriter {
+    /// Create a new, empty status writer for a terminal of the given size.
+    ///
+    /// Status writers are normally created by the log handler, but standalone writers are useful
+    /// for rendering status lines into a buffer, e.g. when implementing a widget that wraps other
+    /// widgets.
+    pub fn new(columns: u16, rows: u16) -> Self {
+        Self {
+            buffer: Vec::new(),
+            columns,
+            rows,
+            severity: None,
+        }
+    }
+
+    /// Return the width of the terminal in columns.
+    pub fn width(&self) -> u16 {
+        self.columns
+    }
+
+    /// Return the height of the terminal in rows.
+    pub fn height(&self) -> u16 {
+        self.rows
+    }
+
+    /// Return the output that was written so far.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Return the level of the most severe log entry that was written in the batch preceding this
+    /// status line, or `None` if the batch contained no entries with a known level.
+    ///
+    /// Levels are only known for log entries written through `MakeWriter::make_writer_for`, which
+    /// `tracing_subscriber`'s formatting layer uses. This can be used to make the status line react
+    /// to errors, e.g. by highlighting it.
+    pub fn severity(&self) -> Option<Level> {
+        self.severity
+    }
+
+    /// Return a new, empty status writer for a terminal of the same size, with the same severity.
+    pub fn nested(&self) -> Self {
+        Self {
+            severity: self.severity,
+            ..Self::new(self.columns, self.rows)
+        }
+    }
+
+    /// Discard the written output and update the terminal size, so that the writer can be reused.
+    pub(crate) fn reset(&mut self, columns: u16, rows: u16) {
+        self.buffer.clear();
+        self.columns = columns;
+        self.rows = rows;
+    }
+
+    /// Set the level of the most severe log entry in the batch preceding the status line.
+    pub(crate) fn set_severity(&mut self, severity: Option<Level>) {
+        self.severity = severity;
+    }
+
+    /// Keep only the first line of the output, truncated to `max_width` columns. The remaining lines
+    /// are replaced with a line that says how many lines were cut if `indicator` is true, and
+    /// discarded otherwise.
+    pub(crate) fn cut_first_line(&mut self, max_width: usize, indicator: bool) {
+        let end = self.buffer.iter().position(|byte| *byte == b'\n').unwrap_or(self.buffer.len());
+        let line = ansi::truncate(&String::from_utf8_lossy(&self.buffer[..end]), max_width, "…");
+
+        match indicator {
+            true => {
+                self.buffer.splice(..end, line.into_bytes());
+                self.cut_lines(1);
+            }
+
+            false => self.buffer = line.into_bytes(),
+        }
+    }
+
+    /// Keep the first `lines` lines of the output, and replace the remaining lines with a single
+    /// line that says how many lines were cut, e.g. `+3 more`. Styles are reset before that line.
+    pub(crate) fn cut_lines(&mut self, lines: usize) {
+        let mut newlines = self.buffer
+            .iter()
+            .enumerate()
+            .filter(|(_, byte)| **byte == b'\n')
+            .map(|(index, _)| index);
+
+        let end = match lines {
+            0 => 0,
+
+            _ => match newlines.nth(lines - 1) {
+                Some(end) => end,
+                None => return,
+            },
+        };
+
+        // Every remaining newline starts another line that is cut
+        let cut = newlines.count() + 1;
+        self.buffer.truncate(end);
+
+        // Writing to a vector can not fail
+        let _ = crossterm::queue!(
+            self.buffer,
+            ResetColor,
+            SetAttribute(Attribute::Reset),
+        );
+
+        let newline = if lines > 0 { "\n" } else { "" };
+        let _ = write!(self.buffer, "{newline}+{cut} more");
+    }
+
+    /// Return the width of each line of the written output in columns.
+    ///
+    /// Escape sequences do not contribute to the width. Carriage returns and cursor movements to a
+    /// column are taken into account, so output that overwrites part of a line is not counted
+    /// twice.
+    pub(crate) fn line_widths(&self) -> Vec<usize> {
+        let output = String::from_utf8_lossy(&self.buffer);
+        let mut widths = Vec::new();
+        let mut column = 0;
+        let mut widest = 0;
+
+        for token in ansi::tokens(&output) {
+            match token {
+                Token::Char('\n') => {
+                    widths.push(widest);
+                    column = 0;
+                    widest = 0;
+                }
+
+                Token::Char('\r') => column = 0,
+                Token::Char(c) => column += ansi::char_width(c),
+
+                // Cursor Horizontal Absolute, as emitted by `MoveToColumn`
+                Token::Escape(escape) if escape.starts_with("\x1b[") && escape.ends_with('G') => {
+                    column = escape[2..escape.len() - 1].parse::<usize>().unwrap_or(1).saturating_sub(1);
+                }
+
+                Token::Escape(_) => (),
+            }
+
+            widest = widest.max(column);
+        }
+
+        widths.push(widest);
+        widths
+    }
+}
+
+impl Write for StatusWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,1015 @@
+//! Utilities for testing status lines.
+//!
+//! The main utility is `VirtualTerminal`, a small in-memory VT100 emulator that can be used as the
+//! output writer of a log handler. After writing some log messages the visible screen, the
+//! scrollback, and the cursor position can be inspected, so that the output of a status line
+//! callback can be asserted on or compared against a snapshot.
+//!
+//! ```
+//! use std::io::Write;
+//!
+//! use tracing_statusbar::Builder;
+//! use tracing_statusbar::testing::VirtualTerminal;
+//! use tracing_subscriber::fmt::MakeWriter;
+//!
+//! let terminal = VirtualTerminal::new(40, 5);
+//!
+//! let handler = Builder::new(terminal.clone())
+//!     .with_callback(|output: &mut tracing_statusbar::StatusWriter| write!(output, "Working"))
+//!     .terminal_size(40, 5)
+//!     .force_terminal(true)
+//!     .finish();
+//!
+//! handler.make_writer().write_all(b"A log message\n").unwrap();
+//!
+//! assert_eq!(terminal.screen(), ["A log message", "Working", "", "", ""]);
+//! assert_eq!(terminal.cursor(), (7, 1));
+//! ```
+
+use std::io::{self, Write};
+use std::mem;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crossterm::style::{Attribute, Color, ContentStyle};
+
+use crate::ansi;
+
+/// The character stored in the second column of a wide character.
+const WIDE_CONTINUATION: char = '\0';
+
+/// A single character cell of a `VirtualTerminal`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cell {
+    /// The character shown in the cell.
+    character: char,
+
+    /// The colors and attributes of the cell.
+    style: ContentStyle,
+}
+
+impl Cell {
+    /// Return the character shown in the cell. Empty cells contain a space, and the second column
+    /// of a wide character contains a NUL character.
+    pub fn character(&self) -> char {
+        self.character
+    }
+
+    /// Return the colors and attributes of the cell.
+    ///
+    /// Colors set using the 16 color SGR codes, or using the 256 color palette indices below 16,
+    /// are reported as the corresponding named `Color`.
+    pub fn style(&self) -> ContentStyle {
+        self.style
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            character: ' ',
+            style: ContentStyle::default(),
+        }
+    }
+}
+
+/// The position and style saved by `SavePosition` (DECSC).
+#[derive(Clone, Copy, Debug, Default)]
+struct SavedCursor {
+    /// The saved cursor position, as column and row.
+    position: (u16, u16),
+
+    /// The saved text style.
+    style: ContentStyle,
+}
+
+/// The state of the escape sequence parser.
+#[derive(Clone, Debug, Default)]
+enum Parser {
+    /// Printing characters.
+    #[default]
+    Ground,
+
+    /// After an escape character.
+    Escape,
+
+    /// After a character set designation, which is followed by a single character.
+    Charset,
+
+    /// Inside a control sequence, collecting its parameter and intermediate bytes.
+    Csi(String),
+
+    /// Inside an operating system command or another string sequence, which is ignored.
+    String,
+
+    /// After an escape character inside a string sequence.
+    StringEscape,
+}
+
+/// The state of a virtual terminal.
+#[derive(Debug)]
+struct Screen {
+    /// The number of columns.
+    columns: u16,
+
+    /// The number of rows.
+    rows: u16,
+
+    /// The visible cells, stored row by row.
+    grid: Vec<Vec<Cell>>,
+
+    /// The lines that were scrolled off the top of the screen, oldest first.
+    scrollback: Vec<String>,
+
+    /// The cursor position, as column and row.
+    cursor: (u16, u16),
+
+    /// True when the cursor is at the last column and the next printed character wraps to the next
+    /// line first.
+    pending_wrap: bool,
+
+    /// True when the cursor is visible.
+    cursor_visible: bool,
+
+    /// The style applied to printed characters.
+    style: ContentStyle,
+
+    /// The position and style saved by `SavePosition`.
+    saved: SavedCursor,
+
+    /// The first and last row of the scroll region, inclusive.
+    region: (u16, u16),
+
+    /// The grid of the main screen and the cursor saved when switching to it, while the alternate
+    /// screen is shown.
+    main: Option<(Vec<Vec<Cell>>, SavedCursor)>,
+
+    /// The state of the escape sequence parser.
+    parser: Parser,
+
+    /// The bytes of an incomplete UTF-8 character.
+    utf8: Vec<u8>,
+}
+
+impl Screen {
+    /// Create a new, empty screen of the given size.
+    fn new(columns: u16, rows: u16) -> Self {
+        let columns = columns.max(1);
+        let rows = rows.max(1);
+
+        Self {
+            columns,
+            rows,
+            grid: vec![vec![Cell::default(); columns.into()]; rows.into()],
+            scrollback: Vec::new(),
+            cursor: (0, 0),
+            pending_wrap: false,
+            cursor_visible: true,
+            style: ContentStyle::default(),
+            saved: SavedCursor::default(),
+            region: (0, rows - 1),
+            main: None,
+            parser: Parser::Ground,
+            utf8: Vec::new(),
+        }
+    }
+
+    /// Return an empty row.
+    fn blank_row(&self) -> Vec<Cell> {
+        vec![Cell::default(); self.columns.into()]
+    }
+
+    /// Return the text of the provided row, without trailing whitespace.
+    fn row_text(row: &[Cell]) -> String {
+        let text: String = row.iter()
+            .map(|cell| cell.character)
+            .filter(|&c| c != WIDE_CONTINUATION)
+            .collect();
+
+        text.trim_end().to_owned()
+    }
+
+    /// Move the cursor to the provided position, clamped to the screen.
+    fn move_to(&mut self, column: u16, row: u16) {
+        self.cursor = (column.min(self.columns - 1), row.min(self.rows - 1));
+        self.pending_wrap = false;
+    }
+
+    /// Return true when the cursor is inside the scroll region.
+    fn in_region(&self) -> bool {
+        (self.region.0..=self.region.1).contains(&self.cursor.1)
+    }
+
+    /// Scroll the contents of the scroll region up by the provided number of rows. Rows that leave
+    /// the top of the main screen are moved to the scrollback.
+    fn scroll_up(&mut self, count: u16) {
+        let (top, bottom) = self.region;
+
+        for _ in 0..count.min(bottom - top + 1) {
+            let row = self.grid.remove(top.into());
+
+            if top == 0 && self.main.is_none() {
+                self.scrollback.push(Self::row_text(&row));
+            }
+
+            self.grid.insert(bottom.into(), self.blank_row());
+        }
+    }
+
+    /// Scroll the contents of the scroll region down by the provided number of rows.
+    fn scroll_down(&mut self, count: u16) {
+        let (top, bottom) = self.region;
+
+        for _ in 0..count.min(bottom - top + 1) {
+            self.grid.remove(bottom.into());
+            self.grid.insert(top.into(), self.blank_row());
+        }
+    }
+
+    /// Move the cursor down a row, scrolling the scroll region when the cursor is on its last row.
+    fn line_feed(&mut self) {
+        self.pending_wrap = false;
+
+        if self.cursor.1 == self.region.1 {
+            self.scroll_up(1);
+        } else if self.cursor.1 < self.rows - 1 {
+            self.cursor.1 += 1;
+        }
+    }
+
+    /// Move the cursor up a row, scrolling the scroll region when the cursor is on its first row.
+    fn reverse_index(&mut self) {
+        self.pending_wrap = false;
+
+        if self.cursor.1 == self.region.0 {
+            self.scroll_down(1);
+        } else if self.cursor.1 > 0 {
+            self.cursor.1 -= 1;
+        }
+    }
+
+    /// Move the cursor to the first column.
+    fn carriage_return(&mut self) {
+        self.cursor.0 = 0;
+        self.pending_wrap = false;
+    }
+
+    /// Print a character at the cursor position and advance the cursor.
+    fn print(&mut self, c: char) {
+        let width = ansi::char_width(c) as u16;
+
+        // Combining characters are not tracked
+        if width == 0 {
+            return;
+        }
+
+        if self.pending_wrap || self.cursor.0 + width > self.columns {
+            self.carriage_return();
+            self.line_feed();
+        }
+
+        let (column, row) = self.cursor;
+        let cells = &mut self.grid[usize::from(row)];
+
+        cells[usize::from(column)] = Cell {
+            character: c,
+            style: self.style,
+        };
+
+        if width == 2 && column + 1 < self.columns {
+            cells[usize::from(column + 1)] = Cell {
+                character: WIDE_CONTINUATION,
+                style: self.style,
+            };
+        }
+
+        if column + width >= self.columns {
+            self.cursor.0 = self.columns - 1;
+            self.pending_wrap = true;
+        } else {
+            self.cursor.0 = column + width;
+        }
+    }
+
+    /// Erase the cells of a row in the provided range of columns.
+    fn erase_cells(&mut self, row: u16, columns: std::ops::Range<u16>) {
+        let cells = &mut self.grid[usize::from(row)];
+
+        for column in columns {
+            cells[usize::from(column)] = Cell::default();
+        }
+    }
+
+    /// Handle a control character.
+    fn control(&mut self, c: char) {
+        match c {
+            // A newline is treated as a carriage return followed by a line feed, like a terminal
+            // in cooked mode does
+            '\n' | '\x0b' | '\x0c' => {
+                self.carriage_return();
+                self.line_feed();
+            }
+
+            '\r' => self.carriage_return(),
+
+            '\x08' => {
+                self.cursor.0 = self.cursor.0.saturating_sub(1);
+                self.pending_wrap = false;
+            }
+
+            '\t' => {
+                let column = (self.cursor.0 / 8 + 1) * 8;
+                self.move_to(column, self.cursor.1);
+            }
+
+            _ => (),
+        }
+    }
+
+    /// Handle an escape sequence consisting of an escape character and a single character.
+    fn escape(&mut self, c: char) {
+        match c {
+            '7' => {
+                self.saved = SavedCursor {
+                    position: self.cursor,
+                    style: self.style,
+                };
+            }
+
+            '8' => {
+                let SavedCursor { position, style } = self.saved;
+                self.move_to(position.0, position.1);
+                self.style = style;
+            }
+
+            'D' => self.line_feed(),
+
+            'E' => {
+                self.carriage_return();
+                self.line_feed();
+            }
+
+            'M' => self.reverse_index(),
+
+            'c' => {
+                let scrollback = mem::take(&mut self.scrollback);
+                *self = Self::new(self.columns, self.rows);
+                self.scrollback = scrollback;
+            }
+
+            _ => (),
+        }
+    }
+
+    /// Handle a control sequence with the provided parameter and intermediate bytes, and final
+    /// character.
+    fn csi(&mut self, parameters: &str, action: char) {
+        let private = parameters.starts_with(['?', '>', '<', '=']);
+        let parameters = parameters.trim_start_matches(['?', '>', '<', '=']);
+
+        let values: Vec<&str> = parameters.split(';').collect();
+
+        // The numeric value of a parameter, ignoring sub-parameters
+        let value = |index: usize| -> u16 {
+            values.get(index)
+                .and_then(|value| value.split(':').next())
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0)
+        };
+
+        // The numeric value of a parameter, where zero means one
+        let count = |index: usize| value(index).max(1);
+
+        let (column, row) = self.cursor;
+
+        if private {
+            if action == 'h' || action == 'l' {
+                for index in 0..values.len() {
+                    match value(index) {
+                        25 => self.cursor_visible = action == 'h',
+                        1049 => self.alternate_screen(action == 'h'),
+                        _ => (),
+                    }
+                }
+            }
+
+            return;
+        }
+
+        match action {
+            'A' => {
+                let top = if self.in_region() { self.region.0 } else { 0 };
+                self.move_to(column, row.saturating_sub(count(0)).max(top));
+            }
+
+            'B' => {
+                let bottom = if self.in_region() { self.region.1 } else { self.rows - 1 };
+                self.move_to(column, row.saturating_add(count(0)).min(bottom));
+            }
+
+            'C' => self.move_to(column.saturating_add(count(0)), row),
+            'D' => self.move_to(column.saturating_sub(count(0)), row),
+
+            'E' => {
+                self.csi(parameters, 'B');
+                self.carriage_return();
+            }
+
+            'F' => {
+                self.csi(parameters, 'A');
+                self.carriage_return();
+            }
+
+            'G' | '`' => self.move_to(count(0) - 1, row),
+            'd' => self.move_to(column, count(0) - 1),
+            'H' | 'f' => self.move_to(count(1) - 1, count(0) - 1),
+
+            'J' => match value(0) {
+                0 => {
+                    self.erase_cells(row, column..self.columns);
+
+                    for row in row + 1..self.rows {
+                        self.erase_cells(row, 0..self.columns);
+                    }
+                }
+
+                1 => {
+                    for row in 0..row {
+                        self.erase_cells(row, 0..self.columns);
+                    }
+
+                    self.erase_cells(row, 0..column + 1);
+                }
+
+                mode => {
+                    for row in 0..self.rows {
+                        self.erase_cells(row, 0..self.columns);
+                    }
+
+                    if mode == 3 {
+                        self.scrollback.clear();
+                    }
+                }
+            }
+
+            'K' => match value(0) {
+                0 => self.erase_cells(row, column..self.columns),
+                1 => self.erase_cells(row, 0..column + 1),
+                _ => self.erase_cells(row, 0..self.columns),
+            }
+
+            'X' => {
+                let end = column.saturating_add(count(0)).min(self.columns);
+                self.erase_cells(row, column..end);
+            }
+
+            'S' => self.scroll_up(count(0)),
+            'T' => self.scroll_down(count(0)),
+
+            'm' => self.sgr(&values),
+
+            'r' => {
+                let top = count(0);
+                let bottom = match value(1) {
+                    0 => self.rows,
+                    bottom => bottom.min(self.rows),
+                };
+
+                if top < bottom {
+                    self.region = (top - 1, bottom - 1);
+                    self.move_to(0, 0);
+                }
+            }
+
+            's' => self.escape('7'),
+            'u' => self.escape('8'),
+
+            _ => (),
+        }
+    }
+
+    /// Handle a Select Graphic Rendition sequence with the provided parameters.
+    fn sgr(&mut self, values: &[&str]) {
+        let mut values = values.iter().copied();
+
+        while let Some(value) = values.next() {
+            let mut parts = value.split(':');
+            let code = parts.next()
+                .and_then(|code| code.parse::<u16>().ok())
+                .unwrap_or(0);
+
+            let attributes = &mut self.style.attributes;
+
+            match code {
+                0 => self.style = ContentStyle::default(),
+                1 => attributes.set(Attribute::Bold),
+                2 => attributes.set(Attribute::Dim),
+                3 => attributes.set(Attribute::Italic),
+
+                4 => {
+                    for attribute in [Attribute::Underlined, Attribute::DoubleUnderlined, Attribute::Undercurled, Attribute::Underdotted, Attribute::Underdashed] {
+                        attributes.unset(attribute);
+                    }
+
+                    match parts.next() {
+                        Some("0") => (),
+                        Some("2") => attributes.set(Attribute::DoubleUnderlined),
+                        Some("3") => attributes.set(Attribute::Undercurled),
+                        Some("4") => attributes.set(Attribute::Underdotted),
+                        Some("5") => attributes.set(Attribute::Underdashed),
+                        _ => attributes.set(Attribute::Underlined),
+                    }
+                }
+
+                5 => attributes.set(Attribute::SlowBlink),
+                6 => attributes.set(Attribute::RapidBlink),
+                7 => attributes.set(Attribute::Reverse),
+                8 => attributes.set(Attribute::Hidden),
+                9 => attributes.set(Attribute::CrossedOut),
+                21 => attributes.set(Attribute::DoubleUnderlined),
+
+                22 => {
+                    attributes.unset(Attribute::Bold);
+                    attributes.unset(Attribute::Dim);
+                }
+
+                23 => attributes.unset(Attribute::Italic),
+
+                24 => {
+                    for attribute in [Attribute::Underlined, Attribute::DoubleUnderlined, Attribute::Undercurled, Attribute::Underdotted, Attribute::Underdashed] {
+                        attributes.unset(attribute);
+                    }
+                }
+
+                25 => {
+                    attributes.unset(Attribute::SlowBlink);
+                    attributes.unset(Attribute::RapidBlink);
+                }
+
+                27 => attributes.unset(Attribute::Reverse),
+                28 => attributes.unset(Attribute::Hidden),
+                29 => attributes.unset(Attribute::CrossedOut),
+                53 => attributes.set(Attribute::OverLined),
+                55 => attributes.unset(Attribute::OverLined),
+
+                30..=37 => self.style.foreground_color = Some(palette(code - 30)),
+                38 => self.style.foreground_color = extended_color(&mut values),
+                39 => self.style.foreground_color = None,
+                40..=47 => self.style.background_color = Some(palette(code - 40)),
+                48 => self.style.background_color = extended_color(&mut values),
+                49 => self.style.background_color = None,
+                58 => self.style.underline_color = extended_color(&mut values),
+                59 => self.style.underline_color = None,
+                90..=97 => self.style.foreground_color = Some(palette(code - 90 + 8)),
+                100..=107 => self.style.background_color = Some(palette(code - 100 + 8)),
+
+                _ => (),
+            }
+        }
+    }
+
+    /// Feed a single byte of output to the terminal.
+    fn feed(&mut self, byte: u8) {
+        match mem::take(&mut self.parser) {
+            Parser::Ground => self.ground(byte),
+
+            Parser::Escape => match byte {
+                b'[' => self.parser = Parser::Csi(String::new()),
+                b']' | b'P' | b'X' | b'^' | b'_' => self.parser = Parser::String,
+                b'(' | b')' | b'*' | b'+' | b'#' | b'%' => self.parser = Parser::Charset,
+                0x1b => self.parser = Parser::Escape,
+                _ => self.escape(char::from(byte)),
+            }
+
+            Parser::Charset => (),
+
+            Parser::Csi(mut parameters) => match byte {
+                0x40..=0x7e => self.csi(&parameters, char::from(byte)),
+                0x1b => self.parser = Parser::Escape,
+
+                0x20..=0x3f => {
+                    parameters.push(char::from(byte));
+                    self.parser = Parser::Csi(parameters);
+                }
+
+                // Control characters are executed in the middle of a control sequence
+                _ => {
+                    self.control(char::from(byte));
+                    self.parser = Parser::Csi(parameters);
+                }
+            }
+
+            Parser::String => match byte {
+                0x07 => (),
+                0x1b => self.parser = Parser::StringEscape,
+                _ => self.parser = Parser::String,
+            }
+
+            Parser::StringEscape => match byte {
+                b'\\' => (),
+                _ => self.parser = Parser::String,
+            }
+        }
+    }
+
+    /// Feed a byte of output to the terminal while printing characters.
+    fn ground(&mut self, byte: u8) {
+        if byte == 0x1b {
+            self.utf8.clear();
+            self.parser = Parser::Escape;
+            return;
+        }
+
+        if byte.is_ascii() {
+            self.utf8.clear();
+
+            match byte.is_ascii_control() {
+                true => self.control(char::from(byte)),
+                false => self.print(char::from(byte)),
+            }
+
+            return;
+        }
+
+        self.utf8.push(byte);
+
+        match std::str::from_utf8(&self.utf8) {
+            Ok(text) => {
+                let text = text.to_owned();
+                self.utf8.clear();
+                text.chars().for_each(|c| self.print(c));
+            }
+
+            // Wait for the remaining bytes of the character
+            Err(error) if error.error_len().is_none() => (),
+
+            Err(_) => {
+                self.utf8.clear();
+                self.print(char::REPLACEMENT_CHARACTER);
+            }
+        }
+    }
+
+    /// Switch to the alternate screen, saving the cursor and clearing the screen, or switch back
+    /// to the main screen and restore the cursor (mode 1049).
+    fn alternate_screen(&mut self, enable: bool) {
+        match (enable, self.main.take()) {
+            (true, None) => {
+                let grid = vec![self.blank_row(); self.rows.into()];
+                let saved = SavedCursor {
+                    position: self.cursor,
+                    style: self.style,
+                };
+
+                self.main = Some((mem::replace(&mut self.grid, grid), saved));
+            }
+
+            (false, Some((grid, saved))) => {
+                self.grid = grid;
+                self.move_to(saved.position.0, saved.position.1);
+                self.style = saved.style;
+            }
+
+            (_, main) => self.main = main,
+        }
+    }
+
+    /// Change the size of the screen.
+    ///
+    /// Lines are not reflowed. When the number of rows shrinks, rows at the top of the screen are
+    /// moved to the scrollback so that the cursor stays on the screen. The scroll region is reset.
+    fn resize(&mut self, columns: u16, rows: u16) {
+        let columns = columns.max(1);
+        let rows = rows.max(1);
+
+        let excess = (self.cursor.1 + 1).saturating_sub(rows);
+
+        for row in self.grid.drain(..usize::from(excess)) {
+            if self.main.is_none() {
+                self.scrollback.push(Self::row_text(&row));
+            }
+        }
+
+        self.cursor.1 -= excess;
+        self.columns = columns;
+        self.rows = rows;
+
+        let main = self.main.as_mut().map(|(grid, _)| grid);
+
+        for grid in std::iter::once(&mut self.grid).chain(main) {
+            grid.resize_with(rows.into(), Vec::new);
+
+            for row in grid {
+                row.resize(columns.into(), Cell::default());
+            }
+        }
+
+        self.region = (0, rows - 1);
+        self.move_to(self.cursor.0, self.cursor.1);
+    }
+}
+
+/// Return the color of the provided index of the 16 color palette.
+fn palette(index: u16) -> Color {
+    const COLORS: [Color; 16] = [
+        Color::Black,
+        Color::DarkRed,
+        Color::DarkGreen,
+        Color::DarkYellow,
+        Color::DarkBlue,
+        Color::DarkMagenta,
+        Color::DarkCyan,
+        Color::Grey,
+        Color::DarkGrey,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::White,
+    ];
+
+    COLORS[usize::from(index % 16)]
+}
+
+/// Parse the parameters of an extended color, i.e. `5;<index>` or `2;<r>;<g>;<b>`, taking them
+/// from the provided iterator.
+fn extended_color<'a>(values: &mut impl Iterator<Item = &'a str>) -> Option<Color> {
+    let mut next = || values.next().and_then(|value| value.parse::<u8>().ok());
+
+    match next()? {
+        5 => match next()? {
+            index @ 0..=15 => Some(palette(index.into())),
+            index => Some(Color::AnsiValue(index)),
+        },
+
+        2 => Some(Color::Rgb {
+            r: next()?,
+            g: next()?,
+            b: next()?,
+        }),
+
+        _ => None,
+    }
+}
+
+/// An in-memory terminal emulator that implements `Write`.
+///
+/// The emulator interprets the escape sequences that are emitted by this crate and by crossterm:
+/// cursor movement, saving and restoring the cursor, erasing, scroll regions, cursor visibility,
+/// and colors and text attributes. Other escape sequences are ignored. A newline moves the cursor
+/// to the start of the next line, like a terminal in cooked mode does.
+///
+/// The terminal is cheap to clone, and all clones share the same screen. This means a clone can be
+/// passed to a log handler as its output, while the original is used to inspect the screen.
+///
+/// The emulator does not report its size to log handlers. Pass the same size to
+/// `Builder::terminal_size` so that status lines are laid out for the right size, and use
+/// `Builder::force_terminal` so that the output is treated as a terminal regardless of the
+/// environment.
+#[derive(Clone)]
+pub struct VirtualTerminal {
+    /// The shared state of the terminal.
+    screen: Arc<Mutex<Screen>>,
+}
+
+impl VirtualTerminal {
+    /// Create a new, empty terminal with the given number of columns and rows.
+    pub fn new(columns: u16, rows: u16) -> Self {
+        Self {
+            screen: Arc::new(Mutex::new(Screen::new(columns, rows))),
+        }
+    }
+
+    /// Lock the shared state of the terminal.
+    fn lock(&self) -> MutexGuard<'_, Screen> {
+        self.screen.lock().expect("Virtual terminal mutex was poisoned")
+    }
+
+    /// Return the size of the terminal as columns and rows.
+    pub fn size(&self) -> (u16, u16) {
+        let screen = self.lock();
+
+        (screen.columns, screen.rows)
+    }
+
+    /// Change the size of the terminal.
+    ///
+    /// Lines are not reflowed. When the number of rows shrinks, rows at the top of the screen are
+    /// moved to the scrollback so that the cursor stays on the screen. The scroll region is reset.
+    pub fn resize(&self, columns: u16, rows: u16) {
+        self.lock().resize(columns, rows);
+    }
+
+    /// Return the cursor position as a 0-based column and row.
+    pub fn cursor(&self) -> (u16, u16) {
+        self.lock().cursor
+    }
+
+    /// Return true when the cursor is visible.
+    pub fn cursor_visible(&self) -> bool {
+        self.lock().cursor_visible
+    }
+
+    /// Return true when the alternate screen is shown.
+    pub fn alternate_screen(&self) -> bool {
+        self.lock().main.is_some()
+    }
+
+    /// Return the scroll region as a 0-based, inclusive range of rows, or `None` when the whole
+    /// screen scrolls.
+    pub fn scroll_region(&self) -> Option<(u16, u16)> {
+        let screen = self.lock();
+
+        match screen.region {
+            (0, bottom) if bottom == screen.rows - 1 => None,
+            region => Some(region),
+        }
+    }
+
+    /// Return the text of every visible row, without trailing whitespace. While the alternate screen
+    /// is shown this returns the rows of the alternate screen.
+    pub fn screen(&self) -> Vec<String> {
+        self.lock()
+            .grid
+            .iter()
+            .map(|row| Screen::row_text(row))
+            .collect()
+    }
+
+    /// Return the text of the provided visible row, without trailing whitespace.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the row is outside of the screen.
+    pub fn row(&self, row: u16) -> String {
+        Screen::row_text(&self.lock().grid[usize::from(row)])
+    }
+
+    /// Return the cell at the provided column and row.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the position is outside of the screen.
+    pub fn cell(&self, column: u16, row: u16) -> Cell {
+        self.lock().grid[usize::from(row)][usize::from(column)]
+    }
+
+    /// Return the text of the lines that were scrolled off the top of the screen, oldest first.
+    pub fn scrollback(&self) -> Vec<String> {
+        self.lock().scrollback.clone()
+    }
+
+    /// Return the scrollback followed by the visible rows as a single string, with empty rows at
+    /// the bottom of the screen removed. This is useful for snapshot tests.
+    pub fn contents(&self) -> String {
+        let mut lines = self.scrollback();
+        lines.extend(self.screen());
+
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+
+        lines.join("\n")
+    }
+}
+
+impl Write for VirtualTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut screen = self.lock();
+
+        for &byte in buf {
+            screen.feed(byte);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crossterm::cursor::{MoveTo, MoveToColumn, MoveUp, RestorePosition, SavePosition};
+    use crossterm::style::{Print, ResetColor, SetAttribute, SetForegroundColor};
+    use crossterm::terminal::{Clear, ClearType};
+
+    #[test]
+    fn newlines_scroll_into_the_scrollback() {
+        let mut terminal = VirtualTerminal::new(10, 2);
+        terminal.write_all(b"one\ntwo\nthree\n").unwrap();
+
+        assert_eq!(terminal.scrollback(), ["one", "two"]);
+        assert_eq!(terminal.screen(), ["three", ""]);
+        assert_eq!(terminal.cursor(), (0, 1));
+    }
+
+    #[test]
+    fn a_full_line_wraps_on_the_next_character() {
+        let mut terminal = VirtualTerminal::new(4, 3);
+        terminal.write_all(b"abcd").unwrap();
+
+        assert_eq!(terminal.cursor(), (3, 0));
+
+        terminal.write_all(b"e").unwrap();
+
+        assert_eq!(terminal.screen(), ["abcd", "e", ""]);
+        assert_eq!(terminal.cursor(), (1, 1));
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        let mut terminal = VirtualTerminal::new(5, 2);
+        terminal.write_all("a界b".as_bytes()).unwrap();
+
+        assert_eq!(terminal.row(0), "a界b");
+        assert_eq!(terminal.cursor(), (4, 0));
+    }
+
+    #[test]
+    fn characters_split_across_writes_are_decoded() {
+        let mut terminal = VirtualTerminal::new(5, 2);
+        let bytes = "é\x1b[31mx".as_bytes();
+
+        for byte in bytes {
+            terminal.write_all(&[*byte]).unwrap();
+        }
+
+        assert_eq!(terminal.row(0), "éx");
+        assert_eq!(terminal.cell(1, 0).style().foreground_color, Some(Color::DarkRed));
+    }
+
+    #[test]
+    fn cursor_movement_and_clearing() {
+        let mut terminal = VirtualTerminal::new(10, 4);
+        terminal.write_all(b"one\ntwo\nthree").unwrap();
+
+        crossterm::queue!(
+            terminal,
+            MoveToColumn(0),
+            MoveUp(1),
+            Clear(ClearType::FromCursorDown),
+            Print("new"),
+        ).unwrap();
+
+        assert_eq!(terminal.screen(), ["one", "new", "", ""]);
+        assert_eq!(terminal.cursor(), (3, 1));
+    }
+
+    #[test]
+    fn colors_and_attributes() {
+        let mut terminal = VirtualTerminal::new(10, 1);
+
+        crossterm::queue!(
+            terminal,
+            SetForegroundColor(Color::Red),
+            SetAttribute(Attribute::Bold),
+            Print("a"),
+            ResetColor,
+            Print("b"),
+        ).unwrap();
+
+        let style = terminal.cell(0, 0).style();
+        assert_eq!(style.foreground_color, Some(Color::Red));
+        assert!(style.attributes.has(Attribute::Bold));
+
+        assert_eq!(terminal.cell(1, 0).style(), ContentStyle::default());
+    }
+
+    #[test]
+    fn scroll_regions_keep_the_bottom_rows_in_place() {
+        let mut terminal = VirtualTerminal::new(10, 3);
+
+        crossterm::queue!(
+            terminal,
+            MoveTo(0, 2),
+            Print("status"),
+            MoveTo(0, 0),
+            SavePosition,
+            Print("\x1b[1;2r"),
+            RestorePosition,
+        ).unwrap();
+
+        assert_eq!(terminal.scroll_region(), Some((0, 1)));
+
+        terminal.write_all(b"one\ntwo\nthree\n").unwrap();
+
+        assert_eq!(terminal.scrollback(), ["one", "two"]);
+        assert_eq!(terminal.screen(), ["three", "", "status"]);
+    }
+
+    #[test]
+    fn shrinking_keeps_the_cursor_on_screen() {
+        let mut terminal = VirtualTerminal::new(10, 4);
+        terminal.write_all(b"one\ntwo\nthree").unwrap();
+        terminal.resize(5, 2);
+
+        assert_eq!(terminal.scrollback(), ["one"]);
+        assert_eq!(terminal.screen(), ["two", "three"]);
+        assert_eq!(terminal.cursor(), (4, 1));
+    }
+}
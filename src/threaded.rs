@@ -1,95 +1,182 @@
 use std::io::{self, Write};
+use std::sync::Arc;
 use std::thread::{self, JoinHandle};
+use std::time::Instant;
 
-use crossterm::cursor::{MoveToColumn, MoveUp};
-use crossterm::style::ResetColor;
-use crossterm::terminal::{Clear, ClearType};
+use tracing::Metadata;
 use tracing_subscriber::fmt::MakeWriter;
 
-use crate::{LogReceiver, LogSender, MakeCallback, RawModeGuard};
-use crate::log_bridge::{self, TryRecvError};
+use crate::{DropCounter, Health, LogReceiver, LogSender, MakeCallback, StatusHandle, StatusWriter};
+use crate::builder::Settings;
+use crate::error::HealthState;
+use crate::log_bridge::{self, Event, RecvTimeoutError, TryRecvError};
+use crate::renderer::Renderer;
+use crate::resize::ResizeWatcher;
 
-/// The entry point for the background log writing thread.
-///
-/// This function takes a receiving channel, status line callback, output writer, and settings. It
-/// will read log entries from the channel and place a status line below them.
+/// Write a batch of log entries, starting with the provided event and followed by any other events
+/// that are already queued, up to `max_entries` entries. Returns true when the channel was closed.
 ///
 /// Incoming log lines are grouped together when they are received faster than they could be
 /// written to the writer. This ensures that the status line callback is not invoked unnecessarily,
 /// i.e. it is not called when its status line would immediately be overwritten by another log
-/// message.
+/// message. The batch size is limited, so that log lines keep reaching the output while log
+/// messages arrive faster than they can be written.
+pub(crate) fn write_batch<T, W>(
+    renderer: &mut Renderer<T, W>,
+    receiver: &mut LogReceiver,
+    event: Event,
+    max_entries: usize,
+) -> bool
+where
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
+    W: Write,
+{
+    let mut entries = Vec::new();
+    let mut panic = None;
+    let mut pager = None;
+    let mut closed = false;
+
+    match event {
+        Event::Entry(entry) => entries.push(entry),
+        Event::Redraw => (),
+        Event::Panic(message) => panic = Some(message),
+        Event::Pager(command) => pager = Some(command),
+        Event::Notify(toast) => renderer.notify(toast),
+    }
+
+    // Grab any additional queued events to reduce unnecessary status line writing. A panic message
+    // or pager command ends the batch, so that it is handled after the entries that preceded it.
+    while panic.is_none() && pager.is_none() && entries.len() < max_entries {
+        match receiver.try_recv() {
+            Ok(Event::Entry(entry)) => entries.push(entry),
+            Ok(Event::Redraw) => (),
+            Ok(Event::Panic(message)) => panic = Some(message),
+            Ok(Event::Pager(command)) => pager = Some(command),
+            Ok(Event::Notify(toast)) => renderer.notify(toast),
+            Err(TryRecvError::Empty) => break,
+
+            Err(TryRecvError::Closed) => {
+                closed = true;
+                break;
+            }
+        }
+    }
+
+    // Report any entries that were discarded by the overflow policy since the previous batch
+    entries.extend(receiver.dropped_marker());
+
+    renderer.write(&entries);
+
+    if let Some(command) = pager {
+        renderer.pager(command);
+    }
+
+    // Stop drawing the status line once the program panics
+    if let Some(panic) = panic {
+        renderer.panic(&panic.message);
+        panic.acknowledge();
+    }
+
+    closed
+}
+
+/// The entry point for the background log writing thread.
+///
+/// This function takes a receiving channel, status line callback, output writer, and settings. It
+/// will read log entries from the channel and place a status line below them. Log entries are
+/// written in batches, see `write_batch`.
+///
+/// When a refresh interval is configured the status line is also redrawn whenever no log entries
+/// arrived during that interval. The same applies to heartbeats when the output is not a terminal.
+///
+/// When the channel is closed all queued log entries are written and the status area is finalized
+/// before the function returns.
 fn handle_logs<T, W>(
     mut receiver: LogReceiver,
-    assume_raw_mode: bool,
-    mut callback: T,
-    mut output: W,
+    settings: Settings,
+    callback: T,
+    output: W,
 )
 where
-    T: FnMut(&mut W) -> io::Result<u16>,
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
     W: Write,
 {
-    let mut lines = 0;
-
-    while let Some(entry) = receiver.recv() {
-        // Move to the beginning of the line and reset the color to default
-        crossterm::queue!(
-            output,
-            MoveToColumn(0),
-            ResetColor,
-        ).expect("Could not write to output");
-
-        // Erase any lines that were written in the previous callback
-        for _ in 0..lines {
-            crossterm::queue!(
-                output,
-                Clear(ClearType::CurrentLine),
-                MoveUp(1),
-            ).expect("Could not write to output");
-        }
-
-        // Erase the current line.
-        crossterm::queue!(
-            output,
-            Clear(ClearType::CurrentLine),
-        ).expect("Could not write to output");
-
-        // Disable raw mode if necessary
-        let raw_mode_guard = if assume_raw_mode {
-            Some(RawModeGuard::new())
-        } else {
-            None
-        };
+    let mut renderer = Renderer::new(callback, output, &settings);
 
-        // Write the log entry
-        let _ = output.write(&entry).expect("Could not write to output");
+    let batch_size = settings.channel_capacity.max(1);
+    let refresh_interval = settings.tick_interval();
+    let mut next_refresh = refresh_interval.map(|interval| Instant::now() + interval);
 
-        // Grab any additional queued entries to reduce unnecessary status line writing
-        loop {
-            match receiver.try_recv() {
-                Ok(entry) => {
-                    let _ = output.write(&entry).expect("Could not write to output");
-                }
+    loop {
+        // Wait for the next event, redrawing the status line when the refresh deadline passes or a
+        // notification expires
+        let event = match renderer.deadline(next_refresh) {
+            Some(deadline) => match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => Event::Redraw,
+                Err(RecvTimeoutError::Closed) => break,
+            }
 
-                Err(TryRecvError::Empty) => break,
-                Err(TryRecvError::Closed) => return,
+            None => match receiver.recv() {
+                Some(event) => event,
+                None => break,
             }
+        };
+
+        if write_batch(&mut renderer, &mut receiver, event, batch_size) {
+            break;
         }
 
-        // Re-enable raw mode if necessary
-        drop(raw_mode_guard);
+        next_refresh = refresh_interval.map(|interval| Instant::now() + interval);
+    }
+
+    renderer.finish();
+}
+
+/// The background thread of a threaded handler, along with everything needed to shut it down.
+struct Worker {
+    /// A sender used to close the channel to the background thread.
+    log_sender: LogSender,
+
+    /// A join handle that represents the background thread.
+    join_handle: Option<JoinHandle<()>>,
+
+    /// An optional watcher that redraws the status line when the terminal is resized.
+    resize_watcher: Option<ResizeWatcher>,
+}
 
-        // Write the status line and track the number of lines written
-        crossterm::execute!(
-            output,
-            MoveToColumn(0),
-        ).expect("Could not write to output");
+/// A `Drop` impl that shuts down and joins the log writing thread.
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // Stop the resize watcher first, so that it does not try to redraw the status line of a
+        // closed channel
+        drop(self.resize_watcher.take());
 
-        lines = callback(&mut output).expect("Could not write to output");
+        // Queued log entries are written before the thread sees the closing message
+        self.log_sender.close();
 
-        output.flush().expect("Could not flush output");
+        // Join writer thread
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.join().expect("The log writing thread paniced");
+        }
     }
 }
 
+/// A guard that shuts down the background thread of a threaded handler when dropped.
+///
+/// The guard is returned by `Builder::finish_with_guard`. It should be kept alive until the end of
+/// `main`, e.g. by assigning it to a `_guard` variable (note that assigning it to `_` drops it
+/// immediately). Dropping the guard writes all queued log messages, finalizes the status area,
+/// and joins the background thread.
+///
+/// Log messages that are written after the guard was dropped are printed to standard output
+/// directly.
+#[must_use = "The background thread is shut down when the guard is dropped"]
+pub struct WorkerGuard {
+    /// The background thread, which is shut down when the guard is dropped.
+    _worker: Worker,
+}
+
 /// A threaded status line log handler.
 ///
 /// The struct implements `MakeWriter`, meaning that instances of this struct can be passed as
@@ -101,13 +188,17 @@ where
 ///
 /// Note that when the hander is used as part of `tracing_subscriber`'s global default subscriber
 /// the handler is never dropped, and thus the background thread will also continue run until the
-/// program is terminated.
+/// program is terminated. Any queued log messages are lost at that point. Use
+/// `Builder::finish_with_guard` to obtain a `WorkerGuard` that shuts down the thread instead.
 pub struct ThreadedHandler {
     /// A sender used to communicate log messages to the background thread.
     log_sender: LogSender,
 
-    /// A join handle that represents the background thread.
-    join_handle: Option<JoinHandle<()>>,
+    /// The background thread. This is `None` when the thread is owned by a `WorkerGuard`.
+    worker: Option<Worker>,
+
+    /// The health of the handler, updated by the background thread whenever an error occurs.
+    health: Arc<HealthState>,
 }
 
 impl ThreadedHandler {
@@ -119,42 +210,77 @@ impl ThreadedHandler {
     pub(crate) fn new<T, W>(
         callback: T,
         output: W,
-        assume_raw_mode: bool,
+        settings: Settings,
     ) -> Self
     where
-        T: MakeCallback<W> + Send + 'static,
+        T: MakeCallback + Send + 'static,
         W: Write + Send + 'static,
     {
-        let (log_sender, log_receiver) = log_bridge::init();
+        let (log_sender, log_receiver) = log_bridge::init(
+            settings.channel_capacity,
+            settings.overflow_policy,
+            settings.drop_counter.clone(),
+        );
+
+        let resize_watcher = match settings.terminal_size {
+            Some(_) => None,
+            None if !settings.terminal() => None,
+            None => ResizeWatcher::new(StatusHandle::threaded(log_sender.clone())),
+        };
+
+        let health = settings.health.clone();
 
         let join_handle = thread::spawn(move || {
             crate::threaded::handle_logs(
                 log_receiver,
-                assume_raw_mode,
+                settings,
                 callback.make_callback(),
                 output,
             )
         });
 
         Self {
-            log_sender,
-            join_handle: Some(join_handle),
+            log_sender: log_sender.clone(),
+            worker: Some(Worker {
+                log_sender,
+                join_handle: Some(join_handle),
+                resize_watcher,
+            }),
+            health,
         }
     }
-}
-
-/// A `Drop` impl that shuts down and joins the log writing thread.
-impl Drop for ThreadedHandler {
-    fn drop(&mut self) {
-        // Note: drop is not guaranteed to be called if self is used as the global default
-        // subscriber.
-        self.log_sender.close();
 
-        // Join writer thread
-        if let Some(join_handle) = self.join_handle.take() {
-            join_handle.join().expect("The log writing thread paniced");
+    /// Move ownership of the background thread into a guard, so that dropping the handler no
+    /// longer shuts down the thread.
+    pub(crate) fn guard(&mut self) -> WorkerGuard {
+        WorkerGuard {
+            _worker: self.worker.take().expect("The background thread is owned by the handler"),
         }
     }
+
+    /// Return the counter that tracks the number of log entries that were discarded because of the
+    /// overflow policy.
+    pub fn drop_counter(&self) -> DropCounter {
+        self.log_sender.drop_counter()
+    }
+
+    /// Return the current health of the handler, i.e. whether any errors occurred while writing
+    /// to the output or invoking the status line callback.
+    pub fn health(&self) -> Health {
+        self.health.snapshot()
+    }
+
+    /// Install a panic hook that erases the status area and restores the terminal before the
+    /// panic is reported. See `StatusHandle::install_panic_hook` for details.
+    pub fn install_panic_hook(&self) {
+        self.handle().install_panic_hook();
+    }
+
+    /// Return a handle that can be used to control the status line from other parts of the
+    /// program, e.g. to redraw it after updating shared state.
+    pub fn handle(&self) -> StatusHandle {
+        StatusHandle::threaded(self.log_sender.clone())
+    }
 }
 
 impl<'a> MakeWriter<'a> for ThreadedHandler {
@@ -163,4 +289,155 @@ impl<'a> MakeWriter<'a> for ThreadedHandler {
     fn make_writer(&'a self) -> Self::Writer {
         self.log_sender.clone()
     }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        self.log_sender.for_event(meta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{FinishMode, OverflowPolicy, SynchronizedOutput};
+    use crate::testing::VirtualTerminal;
+
+    /// Return settings for a fixed size terminal.
+    fn settings() -> Settings {
+        Settings {
+            terminal_size: Some((20, 5)),
+            force_terminal: Some(true),
+            synchronized_output: SynchronizedOutput::Disabled,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn queued_entries_are_written_in_a_single_batch() {
+        let terminal = VirtualTerminal::new(20, 5);
+        let (mut sender, receiver) = log_bridge::init(16, OverflowPolicy::Block, DropCounter::new());
+
+        for count in 0..3 {
+            writeln!(sender, "message {count}").unwrap();
+        }
+
+        sender.close();
+
+        let draws = Cell::new(0);
+
+        handle_logs(receiver, settings(), |output: &mut StatusWriter| {
+            draws.set(draws.get() + 1);
+            write!(output, "status")
+        }, terminal.clone());
+
+        // One draw for the batch, and one for the final status area
+        assert_eq!(draws.get(), 2);
+        assert_eq!(terminal.screen(), ["message 0", "message 1", "message 2", "status", ""]);
+        assert_eq!(terminal.cursor(), (0, 4));
+    }
+
+    #[test]
+    fn batches_are_limited_to_the_channel_capacity() {
+        let terminal = VirtualTerminal::new(20, 8);
+        let (mut sender, receiver) = log_bridge::init(2, OverflowPolicy::Unbounded, DropCounter::new());
+
+        for count in 0..5 {
+            sender.write_all(format!("message {count}\n").as_bytes()).unwrap();
+        }
+
+        sender.close();
+
+        let draws = Cell::new(0);
+
+        let settings = Settings {
+            terminal_size: Some((20, 8)),
+            channel_capacity: 2,
+            ..settings()
+        };
+
+        handle_logs(receiver, settings, |output: &mut StatusWriter| {
+            draws.set(draws.get() + 1);
+            write!(output, "status")
+        }, terminal.clone());
+
+        // One draw for each of the three batches, and one for the final status area
+        assert_eq!(draws.get(), 4);
+        assert_eq!(terminal.contents(), "message 0\nmessage 1\nmessage 2\nmessage 3\nmessage 4\nstatus");
+    }
+
+    #[test]
+    fn closing_writes_the_summary() {
+        let terminal = VirtualTerminal::new(20, 5);
+        let (mut sender, receiver) = log_bridge::init(16, OverflowPolicy::Block, DropCounter::new());
+
+        writeln!(sender, "message").unwrap();
+        sender.close();
+
+        let settings = Settings {
+            on_finish: FinishMode::summary(|output| write!(output, "done")),
+            ..settings()
+        };
+
+        handle_logs(receiver, settings, |output: &mut StatusWriter| write!(output, "status"), terminal.clone());
+
+        assert_eq!(terminal.contents(), "message\ndone");
+        assert_eq!(terminal.cursor(), (0, 2));
+    }
+
+    #[test]
+    fn dropping_the_guard_flushes_the_queue() {
+        let terminal = VirtualTerminal::new(20, 5);
+
+        let (handler, guard) = crate::Builder::new(terminal.clone())
+            .with_callback(|output: &mut StatusWriter| write!(output, "status"))
+            .terminal_size(20, 5)
+            .force_terminal(true)
+            .on_finish(FinishMode::Clear)
+            .threaded()
+            .finish_with_guard();
+
+        for count in 0..10 {
+            writeln!(handler.make_writer(), "message {count}").unwrap();
+        }
+
+        drop(guard);
+
+        assert_eq!(terminal.scrollback().len(), 6);
+        assert_eq!(terminal.screen(), ["message 6", "message 7", "message 8", "message 9", ""]);
+    }
+
+    #[test]
+    fn notifications_expire_without_log_messages() {
+        let terminal = VirtualTerminal::new(20, 5);
+
+        let (handler, guard) = crate::Builder::new(terminal.clone())
+            .with_callback(|output: &mut StatusWriter| write!(output, "status"))
+            .terminal_size(20, 5)
+            .force_terminal(true)
+            .on_finish(FinishMode::Clear)
+            .threaded()
+            .finish_with_guard();
+
+        // Wait until the background thread has drawn the expected status area
+        let wait_for = |expected: [&str; 5]| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+
+            while terminal.screen() != expected && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            assert_eq!(terminal.screen(), expected);
+        };
+
+        handler.handle().notify("config reloaded", Duration::from_millis(100)).unwrap();
+        wait_for(["status", "config reloaded", "", "", ""]);
+        wait_for(["status", "", "", "", ""]);
+
+        drop(guard);
+
+        assert_eq!(terminal.contents(), "");
+    }
 }
@@ -0,0 +1,406 @@
+use std::io::{self, Write};
+use std::panic;
+use std::sync::Arc;
+
+use tokio::runtime;
+use tokio::task::{self, JoinHandle};
+use tokio::time::{self, Instant};
+
+use tracing::Metadata;
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::{DropCounter, Health, LogReceiver, LogSender, MakeCallback, StatusHandle, StatusWriter};
+use crate::builder::Settings;
+use crate::error::HealthState;
+use crate::log_bridge;
+use crate::log_bridge::Event;
+use crate::renderer::Renderer;
+use crate::resize::ResizeWatcher;
+use crate::threaded::write_batch;
+
+/// The entry point for the log writing task.
+///
+/// This is the asynchronous counterpart of the background thread of a threaded handler. It reads
+/// log entries from the channel and places a status line below them, writing log entries in
+/// batches using `write_batch`.
+///
+/// When a refresh interval is configured the status line is also redrawn whenever no log entries
+/// arrived during that interval. The same applies to heartbeats when the output is not a terminal.
+///
+/// Writing to the output writer blocks, so batches are written on the blocking thread pool of the
+/// runtime rather than on the worker thread that runs the task. This includes the delays between
+/// attempts of `ErrorPolicy::Retry`.
+///
+/// When the channel is closed all queued log entries are written and the status area is finalized
+/// before the task completes.
+async fn handle_logs<T, W>(
+    mut receiver: LogReceiver,
+    settings: Settings,
+    callback: T,
+    output: W,
+)
+where
+    T: FnMut(&mut StatusWriter) -> io::Result<()> + Send + 'static,
+    W: Write + Send + 'static,
+{
+    let mut renderer = Renderer::new(callback, output, &settings);
+
+    let batch_size = settings.channel_capacity.max(1);
+    let refresh_interval = settings.tick_interval();
+    let mut next_refresh = refresh_interval.map(|interval| Instant::now() + interval);
+
+    loop {
+        // Wait for the next event, redrawing the status line when the refresh deadline passes or a
+        // notification expires
+        let deadline = renderer.deadline(next_refresh.map(Instant::into_std));
+
+        let event = match deadline.map(Instant::from_std) {
+            Some(deadline) => match time::timeout_at(deadline, receiver.recv_async()).await {
+                Ok(Some(event)) => event,
+                Ok(None) => break,
+                Err(_) => Event::Redraw,
+            }
+
+            None => match receiver.recv_async().await {
+                Some(event) => event,
+                None => break,
+            }
+        };
+
+        let closed;
+
+        (renderer, receiver, closed) = blocking(move || {
+            let closed = write_batch(&mut renderer, &mut receiver, event, batch_size);
+            (renderer, receiver, closed)
+        }).await;
+
+        if closed {
+            break;
+        }
+
+        next_refresh = refresh_interval.map(|interval| Instant::now() + interval);
+    }
+
+    blocking(move || renderer.finish()).await;
+}
+
+/// Run the provided function on the blocking thread pool of the runtime and return its result.
+///
+/// A panic in the function is resumed in the calling task, so that it is reported by
+/// `TokioGuard::shutdown` in the same way as a panic in the task itself.
+async fn blocking<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    match task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(error) if error.is_panic() => panic::resume_unwind(error.into_panic()),
+
+        // Blocking tasks are only cancelled when the runtime shuts down, which also cancels this task
+        Err(_) => std::future::pending().await,
+    }
+}
+
+/// The log writing task of a Tokio handler, along with everything needed to shut it down.
+struct Worker {
+    /// A sender used to close the channel to the task.
+    log_sender: LogSender,
+
+    /// A join handle that represents the task.
+    join_handle: Option<JoinHandle<()>>,
+
+    /// An optional watcher that redraws the status line when the terminal is resized.
+    resize_watcher: Option<ResizeWatcher>,
+}
+
+impl Worker {
+    /// Stop the resize watcher and close the channel to the task, without waiting for the task to
+    /// complete.
+    fn close(&mut self) {
+        // Stop the resize watcher first, so that it does not try to redraw the status line of a
+        // closed channel
+        drop(self.resize_watcher.take());
+
+        // Queued log entries are written before the task sees the closing message
+        self.log_sender.close();
+    }
+}
+
+/// A `Drop` impl that closes the channel to the log writing task.
+///
+/// The task is not awaited, because that is not possible from a synchronous context. It will write
+/// the remaining log messages as long as the runtime is running.
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// A guard that owns the log writing task of a Tokio handler.
+///
+/// The guard is returned by `Builder::finish_with_guard`. Call `shutdown` in the graceful shutdown
+/// path of the program to write all queued log messages, finalize the status area, and wait for
+/// the task to complete.
+///
+/// Dropping the guard without calling `shutdown` closes the channel, but does not wait for the task.
+/// Log messages that are written after the channel was closed are printed to standard output
+/// directly.
+#[must_use = "The log writing task is shut down when the guard is dropped"]
+pub struct TokioGuard {
+    /// The log writing task, which is shut down when the guard is dropped.
+    worker: Worker,
+}
+
+impl TokioGuard {
+    /// Close the channel to the log writing task and wait until it has written all queued log
+    /// messages and finalized the status area.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the log writing task paniced.
+    pub async fn shutdown(mut self) {
+        self.worker.close();
+
+        if let Some(join_handle) = self.worker.join_handle.take() {
+            if let Err(error) = join_handle.await {
+                if error.is_panic() {
+                    panic!("The log writing task paniced");
+                }
+            }
+        }
+    }
+}
+
+/// A status line log handler that writes log messages using a task on a Tokio runtime.
+///
+/// The struct implements `MakeWriter`, meaning that instances of this struct can be passed as
+/// writers to the `tracing_subscriber` crate so that the status line will always be written below
+/// the most recently emitted log message.
+///
+/// This struct owns a task that does the actual writing to the provided `Write` impl. The channel
+/// to the task is closed when the handler is dropped, but the task is not awaited. Use
+/// `Builder::finish_with_guard` to obtain a `TokioGuard` whose `shutdown` method can be awaited
+/// instead.
+pub struct TokioHandler {
+    /// A sender used to communicate log messages to the task.
+    log_sender: LogSender,
+
+    /// The log writing task. This is `None` when the task is owned by a `TokioGuard`.
+    worker: Option<Worker>,
+
+    /// The health of the handler, updated by the task whenever an error occurs.
+    health: Arc<HealthState>,
+}
+
+impl TokioHandler {
+    /// Initialize a new handler using the provided status line callback maker, runtime, writer,
+    /// and settings.
+    ///
+    /// The status line callback is created inside the task, but it is held across await points and
+    /// moved to the blocking thread pool, so it must implement `Send + 'static` as well.
+    pub(crate) fn new<T, W>(
+        callback: T,
+        runtime: runtime::Handle,
+        output: W,
+        settings: Settings,
+    ) -> Self
+    where
+        T: MakeCallback + Send + 'static,
+        T::Callback: Send + 'static,
+        W: Write + Send + 'static,
+    {
+        let (log_sender, log_receiver) = log_bridge::init(
+            settings.channel_capacity,
+            settings.overflow_policy,
+            settings.drop_counter.clone(),
+        );
+
+        let resize_watcher = match settings.terminal_size {
+            Some(_) => None,
+            None if !settings.terminal() => None,
+            None => ResizeWatcher::new(StatusHandle::threaded(log_sender.clone())),
+        };
+
+        let health = settings.health.clone();
+
+        let join_handle = runtime.spawn(async move {
+            handle_logs(
+                log_receiver,
+                settings,
+                callback.make_callback(),
+                output,
+            ).await
+        });
+
+        Self {
+            log_sender: log_sender.clone(),
+            worker: Some(Worker {
+                log_sender,
+                join_handle: Some(join_handle),
+                resize_watcher,
+            }),
+            health,
+        }
+    }
+
+    /// Move ownership of the log writing task into a guard, so that dropping the handler no longer
+    /// shuts down the task.
+    pub(crate) fn guard(&mut self) -> TokioGuard {
+        TokioGuard {
+            worker: self.worker.take().expect("The log writing task is owned by the handler"),
+        }
+    }
+
+    /// Return the counter that tracks the number of log entries that were discarded because of the
+    /// overflow policy.
+    pub fn drop_counter(&self) -> DropCounter {
+        self.log_sender.drop_counter()
+    }
+
+    /// Return the current health of the handler, i.e. whether any errors occurred while writing
+    /// to the output or invoking the status line callback.
+    pub fn health(&self) -> Health {
+        self.health.snapshot()
+    }
+
+    /// Install a panic hook that erases the status area and restores the terminal before the
+    /// panic is reported. See `StatusHandle::install_panic_hook` for details.
+    ///
+    /// The hook waits for the log writing task to write the panic message. On a `current_thread`
+    /// runtime the task can not run while a task on the same runtime panics, because the hook
+    /// blocks the only runtime thread. The hook then gives up after half a second, leaving the
+    /// status area in place, and the panic is only reported by the previous panic hook.
+    pub fn install_panic_hook(&self) {
+        self.handle().install_panic_hook();
+    }
+
+    /// Return a handle that can be used to control the status line from other parts of the
+    /// program, e.g. to redraw it after updating shared state.
+    pub fn handle(&self) -> StatusHandle {
+        StatusHandle::threaded(self.log_sender.clone())
+    }
+}
+
+impl<'a> MakeWriter<'a> for TokioHandler {
+    type Writer = LogSender;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.log_sender.clone()
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        self.log_sender.for_event(meta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::thread::{self, ThreadId};
+
+    use tokio::runtime::Handle;
+
+    use super::*;
+    use crate::{Builder, FinishMode};
+    use crate::testing::VirtualTerminal;
+
+    /// Create a single-threaded runtime, on which the log writing task can only run while the test
+    /// awaits something.
+    fn current_thread_runtime() -> runtime::Runtime {
+        runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("Could not create a Tokio runtime")
+    }
+
+    #[test]
+    fn full_queue_does_not_block_a_current_thread_runtime() {
+        let terminal = VirtualTerminal::new(30, 6);
+
+        current_thread_runtime().block_on(async {
+            let (handler, guard) = Builder::new(terminal.clone())
+                .with_callback(|output: &mut StatusWriter| write!(output, "status"))
+                .terminal_size(30, 6)
+                .force_terminal(true)
+                .on_finish(FinishMode::Clear)
+                .tokio(Handle::current())
+                .channel_capacity(4)
+                .finish_with_guard();
+
+            // The log writing task can not run until the test awaits, so the queue fills up
+            for count in 0..10 {
+                handler.make_writer().write_all(format!("message {count}\n").as_bytes()).unwrap();
+            }
+
+            assert_eq!(handler.drop_counter().get(), 6);
+
+            guard.shutdown().await;
+        });
+
+        assert_eq!(
+            terminal.contents(),
+            "message 6\nmessage 7\nmessage 8\nmessage 9\n[6 log lines dropped]",
+        );
+    }
+
+    #[test]
+    fn batches_are_written_off_the_runtime_thread() {
+        /// A writer that records the threads it was written from.
+        #[derive(Clone, Default)]
+        struct ThreadRecorder(Arc<Mutex<Vec<ThreadId>>>);
+
+        impl Write for ThreadRecorder {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().push(thread::current().id());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let recorder = ThreadRecorder::default();
+
+        current_thread_runtime().block_on(async {
+            let (handler, guard) = Builder::new(recorder.clone())
+                .with_callback(|output: &mut StatusWriter| write!(output, "status"))
+                .terminal_size(30, 6)
+                .force_terminal(true)
+                .tokio(Handle::current())
+                .finish_with_guard();
+
+            handler.make_writer().write_all(b"message\n").unwrap();
+
+            guard.shutdown().await;
+        });
+
+        let threads = recorder.0.lock().unwrap();
+
+        assert!(!threads.is_empty());
+        assert!(!threads.contains(&thread::current().id()));
+    }
+
+    #[test]
+    fn shutdown_flushes_the_queue() {
+        let terminal = VirtualTerminal::new(30, 6);
+
+        current_thread_runtime().block_on(async {
+            let (handler, guard) = Builder::new(terminal.clone())
+                .with_callback(|output: &mut StatusWriter| write!(output, "status"))
+                .terminal_size(30, 6)
+                .force_terminal(true)
+                .tokio(Handle::current())
+                .finish_with_guard();
+
+            for count in 0..3 {
+                handler.make_writer().write_all(format!("message {count}\n").as_bytes()).unwrap();
+            }
+
+            guard.shutdown().await;
+        });
+
+        assert_eq!(terminal.contents(), "message 0\nmessage 1\nmessage 2\nstatus");
+    }
+}
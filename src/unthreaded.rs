@@ -1,58 +1,40 @@
 use std::io::{self, Write};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
-use crossterm::cursor::{MoveToColumn, MoveUp};
-use crossterm::style::ResetColor;
-use crossterm::terminal::{Clear, ClearType};
+use tracing::{Level, Metadata};
+use tracing::callsite::Identifier;
 use tracing_subscriber::fmt::MakeWriter;
 
-use crate::RawModeGuard;
+use crate::{Health, StatusHandle, StatusWriter};
+use crate::builder::Settings;
+use crate::error::HealthState;
+use crate::renderer::{Renderer, TaggedEntry};
+use crate::resize::ResizeWatcher;
 
-/// The internal state for a `LogWriter` instance.
-struct WriteState<T, W>
+/// The state of an unthreaded handler, which is shared by its log writers, its ticker thread, and
+/// the status handles obtained from it.
+pub(crate) struct Shared<T, W>
 where
-    T: FnMut(&mut W) -> io::Result<u16>,
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
     W: Write,
 {
-    /// The status line callback that will be invoked after every log message.
-    callback: T,
+    /// The renderer that draws the log messages and the status area.
+    pub(crate) renderer: Mutex<Renderer<T, W>>,
 
-    /// The output writer used to write log messages and status lines to.
-    output: W,
+    /// A condition variable that wakes up the ticker thread, so that it picks up a new deadline
+    /// after a notification was shown, or stops when the handler is dropped.
+    pub(crate) wake: Condvar,
 
-    /// When true the wrapped writer is assumed to be a terminal that is using raw mode. This will
-    /// ensure that the raw mode is temporarily disabled when writing log messages. This prevents
-    /// screen corruption.
-    assume_raw_mode: bool,
+    /// Whether the ticker thread should stop. This is only set while holding the renderer lock,
+    /// so that the ticker thread can not miss the wake up.
+    stopped: AtomicBool,
 
-    /// The number of status lines written in the previous invocation of the status line callback.
-    /// This is used to properly clean up the previous status lines when a new log message should
-    /// be written.
-    lines: u16,
-}
-
-impl<T, W> WriteState<T, W>
-where
-    T: FnMut(&mut W) -> io::Result<u16>,
-    W: Write,
-{
-    /// Initialize a new write state using the provided status line callback, output writer, and
-    /// settings.
-    fn new(callback: T, output: W, assume_raw_mode: bool) -> Self {
-        Self {
-            callback,
-            output,
-            assume_raw_mode,
-            lines: 0,
-        }
-    }
-
-    /// Invoke the status line callback.
-    ///
-    /// A wrapper function is used to assist the compiler with type inference.
-    fn invoke_callback(&mut self) -> io::Result<u16> {
-        (self.callback)(&mut self.output)
-    }
+    /// The ticker thread, which is started when a refresh interval or heartbeat is configured, or
+    /// when the first notification is shown.
+    ticker: Mutex<Option<JoinHandle<()>>>,
 }
 
 /// A writer that will forward any data written to it, and follow this up with an invocation to a
@@ -61,46 +43,62 @@ where
 /// The writer has internal state that is wrapped in an `Arc` and thus can be cloned freely.
 pub struct LogWriter<T, W>
 where
-    T: FnMut(&mut W) -> io::Result<u16>,
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
     W: Write,
 {
     /// The internal state of the log writer.
-    state: Arc<Mutex<WriteState<T, W>>>,
+    state: Arc<Shared<T, W>>,
+
+    /// The level, target, and callsite of the event that is being written, or `None` when entries
+    /// are not written for a specific event.
+    event: Option<(Level, String, Identifier)>,
 }
 
 impl<T, W> Clone for LogWriter<T, W>
 where
-    T: FnMut(&mut W) -> io::Result<u16>,
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
     W: Write,
 {
     fn clone(&self) -> Self {
         Self {
             state: self.state.clone(),
+            event: self.event.clone(),
         }
     }
 }
 
 impl<T, W> LogWriter<T, W>
 where
-    T: FnMut(&mut W) -> io::Result<u16>,
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
     W: Write,
 {
     /// Initialize a new log writer using the provided status line callback, output writer, and
     /// settings.
-    fn new(callback: T, output: W, assume_raw_mode: bool) -> Self {
+    fn new(callback: T, output: W, settings: &Settings) -> Self {
+        Self {
+            state: Arc::new(Shared {
+                renderer: Mutex::new(Renderer::new(callback, output, settings)),
+                wake: Condvar::new(),
+                stopped: AtomicBool::new(false),
+                ticker: Mutex::new(None),
+            }),
+            event: None,
+        }
+    }
+
+    /// Return a writer that tags the entries written to it with the level, target, and callsite
+    /// of the provided event metadata.
+    fn for_event(&self, metadata: &Metadata<'_>) -> Self {
         Self {
-            state: Arc::new(Mutex::new(WriteState::new(
-                callback,
-                output,
-                assume_raw_mode,
-            ))),
+            state: self.state.clone(),
+            event: Some((*metadata.level(), metadata.target().to_owned(), metadata.callsite())),
         }
     }
 }
 
 impl<T, W> Write for LogWriter<T, W>
 where
-    T: FnMut(&mut W) -> io::Result<u16>,
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
     W: Write,
 {
     /// Take the provided buffer and write it to the wrapped writer, invoking the status line
@@ -113,61 +111,104 @@ where
     /// The wrapped output writer is flushed after writing a status line, ensuring that status
     /// lines that don't end with newlines are still visible in terminal environments that use
     /// cooked mode.
+    ///
+    /// Errors are handled according to the handler's error policy instead of being returned,
+    /// because `tracing_subscriber` discards them.
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let mut state = self.state.lock().expect("Log writer state mutex was poisoned");
-
-        // Move to the beginning of the line and reset the color to default
-        crossterm::queue!(
-            state.output,
-            MoveToColumn(0),
-            ResetColor,
-        )?;
-
-        // Erase any lines that were written in the previous callback
-        for _ in 0..state.lines {
-            crossterm::queue!(
-                state.output,
-                Clear(ClearType::CurrentLine),
-                MoveUp(1),
-            )?;
-        }
+        let mut state = self.state.renderer.lock().expect("Log writer state mutex was poisoned");
 
-        // Erase the current line.
-        crossterm::queue!(
-            state.output,
-            Clear(ClearType::CurrentLine),
-        )?;
+        state.write([TaggedEntry {
+            bytes: buf,
+            event: self.event.as_ref().map(|(level, target, callsite)| (*level, target.as_str(), callsite)),
+        }]);
 
-        // Disable raw mode if necessary
-        let raw_mode_guard = if state.assume_raw_mode {
-            Some(RawModeGuard::new())
-        } else {
-            None
-        };
+        Ok(buf.len())
+    }
 
-        // Write the log entry
-        let bytes_written = state.output.write(buf)?;
+    fn flush(&mut self) -> io::Result<()> {
+        let mut state = self.state.renderer.lock().expect("Log writer state mutex was poisoned");
 
-        // Re-enable raw mode if necessary
-        drop(raw_mode_guard);
+        state.flush();
 
-        // Write the status line and track the number of lines written
-        crossterm::execute!(
-            state.output,
-            MoveToColumn(0),
-        )?;
+        Ok(())
+    }
+}
+
+impl<T, W> Shared<T, W>
+where
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
+    W: Write,
+{
+    /// Stop and join the ticker thread, if it was started. The ticker is not started again after
+    /// this.
+    fn stop_ticker(&self) {
+        // The flag is set while holding the lock, so that the ticker thread is either waiting for
+        // the wake up, or sees the flag before it starts waiting
+        let renderer = self.renderer.lock();
+        self.stopped.store(true, Ordering::Relaxed);
+        drop(renderer);
 
-        state.lines = state.invoke_callback()?;
+        self.wake.notify_all();
 
-        state.output.flush()?;
+        let join_handle = self.ticker.lock().unwrap_or_else(PoisonError::into_inner).take();
 
-        Ok(bytes_written)
+        // The ticker thread invokes the status line callback, which may have paniced. That is not
+        // reported again here, because the handler may be dropped while unwinding from that panic.
+        if let Some(join_handle) = join_handle {
+            let _ = join_handle.join();
+        }
     }
+}
 
-    fn flush(&mut self) -> io::Result<()> {
-        let mut state = self.state.lock().expect("Log writer state mutex was poisoned");
+impl<T, W> Shared<T, W>
+where
+    T: FnMut(&mut StatusWriter) -> io::Result<()> + Send + 'static,
+    W: Write + Send + 'static,
+{
+    /// Start the ticker thread unless it is running already, or was stopped.
+    ///
+    /// The ticker redraws the status area when no log messages arrive, i.e. every `interval` if
+    /// provided, and whenever a notification expires.
+    pub(crate) fn start_ticker(self: &Arc<Self>, interval: Option<Duration>) {
+        let mut ticker = self.ticker.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if ticker.is_some() || self.stopped.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let state = self.clone();
+
+        *ticker = Some(thread::spawn(move || {
+            let mut next_refresh = interval.map(|interval| Instant::now() + interval);
+
+            // A poisoned mutex means that a log writer paniced, there is nothing left to redraw in
+            // that case
+            let Ok(mut renderer) = state.renderer.lock() else {
+                return;
+            };
+
+            while !state.stopped.load(Ordering::Relaxed) {
+                let now = Instant::now();
+
+                renderer = match renderer.deadline(next_refresh) {
+                    Some(deadline) if deadline <= now => {
+                        renderer.redraw();
+                        next_refresh = interval.map(|interval| now + interval);
+                        continue;
+                    }
 
-        state.output.flush()
+                    Some(deadline) => match state.wake.wait_timeout(renderer, deadline - now) {
+                        Ok((renderer, _)) => renderer,
+                        Err(_) => return,
+                    }
+
+                    None => match state.wake.wait(renderer) {
+                        Ok(renderer) => renderer,
+                        Err(_) => return,
+                    }
+                };
+            }
+        }));
     }
 }
 
@@ -176,32 +217,112 @@ where
 /// The struct implements `MakeWriter`, meaning that instances of this struct can be passed as
 /// writers to the `tracing_subscriber` crate so that the status line will always be written below
 /// the most recently emitted log message.
+///
+/// Handlers created by `Builder::finish` do not use any threads. Handlers created by
+/// `Builder::finish_with_threads` own a small background thread that redraws the status line
+/// every refresh interval, or prints heartbeats when the output is not a terminal, if configured.
+/// On Unix platforms another thread redraws the status line when the terminal is resized. Showing
+/// a notification starts the first of these threads if it is not running yet, so that the status
+/// line is redrawn when the notification expires. Log messages are always
+/// written on the thread that emits them. These threads are joined when the handler is dropped,
+/// after which the status area is finalized as configured by `Builder::on_finish`.
 pub struct UnthreadedHandler<T, W>
 where
-    T: FnMut(&mut W) -> io::Result<u16>,
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
     W: Write,
 {
     /// The actual writer used for writing log messages. This is cloned on every `make_writer`
     /// invocation.
     writer: LogWriter<T, W>,
+
+    /// An optional watcher that redraws the status line when the terminal is resized.
+    resize_watcher: Option<ResizeWatcher>,
+
+    /// The health of the handler, updated by the writer whenever an error occurs.
+    health: Arc<HealthState>,
 }
 
 impl<T, W> UnthreadedHandler<T, W>
 where
-    T: FnMut(&mut W) -> io::Result<u16>,
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
     W: Write,
 {
-    /// Initialize a new handler using the provided status line callback, writer, and settings.
-    pub(crate) fn new(callback: T, output: W, assume_raw_mode: bool) -> Self {
+    /// Initialize a new handler using the provided status line callback, writer, and settings,
+    /// without any companion threads.
+    pub(crate) fn new(callback: T, output: W, settings: Settings) -> Self {
         Self {
-            writer: LogWriter::new(callback, output, assume_raw_mode),
+            writer: LogWriter::new(callback, output, &settings),
+            resize_watcher: None,
+            health: settings.health,
+        }
+    }
+
+    /// Return the current health of the handler, i.e. whether any errors occurred while writing
+    /// to the output or invoking the status line callback.
+    pub fn health(&self) -> Health {
+        self.health.snapshot()
+    }
+}
+
+impl<T, W> UnthreadedHandler<T, W>
+where
+    T: FnMut(&mut StatusWriter) -> io::Result<()> + Send + 'static,
+    W: Write + Send + 'static,
+{
+    /// Initialize a new handler using the provided status line callback, writer, and settings,
+    /// along with the companion threads that redraw the status line when no log messages arrive.
+    pub(crate) fn with_threads(callback: T, output: W, settings: Settings) -> Self {
+        let interval = settings.tick_interval();
+        let watch_resizes = settings.terminal() && settings.terminal_size.is_none();
+
+        let mut handler = Self::new(callback, output, settings);
+
+        if interval.is_some() {
+            handler.writer.state.start_ticker(interval);
+        }
+
+        if watch_resizes {
+            handler.resize_watcher = ResizeWatcher::new(handler.handle());
+        }
+
+        handler
+    }
+
+    /// Install a panic hook that erases the status area and restores the terminal before the
+    /// panic is reported. See `StatusHandle::install_panic_hook` for details.
+    pub fn install_panic_hook(&self) {
+        self.handle().install_panic_hook();
+    }
+
+    /// Return a handle that can be used to control the status line from other parts of the
+    /// program, e.g. to redraw it after updating shared state.
+    pub fn handle(&self) -> StatusHandle {
+        StatusHandle::unthreaded(self.writer.state.clone())
+    }
+}
+
+/// A `Drop` impl that stops the companion threads and finalizes the status area.
+impl<T, W> Drop for UnthreadedHandler<T, W>
+where
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
+    W: Write,
+{
+    fn drop(&mut self) {
+        // Stop the companion threads first, so that they do not redraw the finalized status area
+        self.writer.state.stop_ticker();
+        drop(self.resize_watcher.take());
+
+        // A poisoned mutex means that a log writer paniced, there is nothing left to finalize in
+        // that case
+        if let Ok(mut state) = self.writer.state.renderer.lock() {
+            state.finish();
         }
     }
 }
 
 impl<'a, T, W> MakeWriter<'a> for UnthreadedHandler<T, W>
 where
-    T: FnMut(&mut W) -> io::Result<u16>,
+    T: FnMut(&mut StatusWriter) -> io::Result<()>,
     W: Write,
 {
     type Writer = LogWriter<T, W>;
@@ -209,4 +330,335 @@ where
     fn make_writer(&'a self) -> Self::Writer {
         self.writer.clone()
     }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        self.writer.for_event(meta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use crossterm::event::{Event, KeyCode};
+    use crossterm::style::{Color, ContentStyle, Stylize};
+    use tracing::{Level, error, info};
+    use tracing_subscriber::fmt::MakeWriter;
+
+    use crate::{Builder, FinishMode, StatusWriter};
+    use crate::testing::VirtualTerminal;
+
+    /// Run the provided closure with a formatting subscriber that writes bare messages to the
+    /// provided handler.
+    fn with_subscriber<M>(writer: M, f: impl FnOnce())
+    where
+        M: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+    {
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(writer)
+            .with_ansi(false)
+            .without_time()
+            .with_target(false)
+            .with_level(false)
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, f);
+    }
+
+    #[test]
+    fn status_line_follows_the_log_messages() {
+        let terminal = VirtualTerminal::new(20, 5);
+
+        let handler = Builder::new(terminal.clone())
+            .with_callback(|output: &mut StatusWriter| write!(output, "status"))
+            .terminal_size(20, 5)
+            .force_terminal(true)
+            .finish();
+
+        handler.make_writer().write_all(b"first\n").unwrap();
+        handler.make_writer().write_all(b"second\n").unwrap();
+
+        assert_eq!(terminal.screen(), ["first", "second", "status", "", ""]);
+        assert_eq!(terminal.cursor(), (6, 2));
+    }
+
+    #[test]
+    fn wrapped_status_lines_are_erased() {
+        let terminal = VirtualTerminal::new(10, 6);
+
+        let handler = Builder::new(terminal.clone())
+            .with_callback(|output: &mut StatusWriter| write!(output, "a status that wraps\nsecond"))
+            .terminal_size(10, 6)
+            .force_terminal(true)
+            .finish();
+
+        handler.make_writer().write_all(b"first\n").unwrap();
+        handler.make_writer().write_all(b"second\n").unwrap();
+
+        assert_eq!(terminal.screen(), ["first", "second", "a status t", "hat wraps", "second", ""]);
+    }
+
+    #[test]
+    fn plain_output_has_no_status_line() {
+        let terminal = VirtualTerminal::new(20, 5);
+
+        let handler = Builder::new(terminal.clone())
+            .with_callback(|output: &mut StatusWriter| write!(output, "status"))
+            .terminal_size(20, 5)
+            .force_terminal(false)
+            .finish();
+
+        handler.make_writer().write_all(b"first\n").unwrap();
+        drop(handler);
+
+        assert_eq!(terminal.contents(), "first");
+    }
+
+    #[test]
+    fn dropping_the_handler_finalizes_the_status_area() {
+        let terminal = VirtualTerminal::new(20, 5);
+
+        let handler = Builder::new(terminal.clone())
+            .with_callback(|output: &mut StatusWriter| write!(output, "status"))
+            .terminal_size(20, 5)
+            .force_terminal(true)
+            .on_finish(FinishMode::Clear)
+            .finish();
+
+        handler.make_writer().write_all(b"first\n").unwrap();
+        drop(handler);
+
+        assert_eq!(terminal.contents(), "first");
+        assert_eq!(terminal.cursor(), (0, 1));
+    }
+
+    #[test]
+    fn events_are_styled_routed_and_reported_by_level() {
+        let terminal = VirtualTerminal::new(30, 5);
+        let secondary = VirtualTerminal::new(30, 5);
+
+        let handler = Builder::new(terminal.clone())
+            .with_callback(|output: &mut StatusWriter| match output.severity() {
+                Some(Level::ERROR) => write!(output, "failing"),
+                _ => write!(output, "ok"),
+            })
+            .terminal_size(30, 5)
+            .force_terminal(true)
+            .level_style(Level::ERROR, ContentStyle::new().red())
+            .route("noisy", secondary.clone())
+            .finish();
+
+        with_subscriber(handler, || {
+            info!("first");
+            info!(target: "noisy::module", "routed");
+            error!("broken");
+        });
+
+        assert_eq!(terminal.contents(), "first\nbroken\nfailing");
+        assert_eq!(terminal.cell(0, 1).style().foreground_color, Some(Color::Red));
+        assert_eq!(terminal.cell(0, 0).style().foreground_color, None);
+        assert_eq!(secondary.contents(), "routed");
+    }
+
+    #[test]
+    fn repeated_entries_are_collapsed() {
+        let terminal = VirtualTerminal::new(30, 5);
+
+        let handler = Builder::new(terminal.clone())
+            .with_callback(|output: &mut StatusWriter| write!(output, "status"))
+            .terminal_size(30, 5)
+            .force_terminal(true)
+            .collapse_repeats()
+            .finish();
+
+        for entry in ["first", "retrying", "retrying", "retrying"] {
+            handler.make_writer().write_all(format!("{entry}\n").as_bytes()).unwrap();
+        }
+
+        assert_eq!(terminal.screen(), ["first", "retrying (repeated 3 times)", "status", "", ""]);
+
+        handler.make_writer().write_all(b"done\n").unwrap();
+
+        assert_eq!(terminal.screen(), ["first", "retrying (repeated 3 times)", "done", "status", ""]);
+    }
+
+    #[test]
+    fn repeated_events_with_timestamps_are_collapsed() {
+        let terminal = VirtualTerminal::new(120, 5);
+
+        let handler = Builder::new(terminal.clone())
+            .with_callback(|output: &mut StatusWriter| write!(output, "status"))
+            .terminal_size(120, 5)
+            .force_terminal(true)
+            .collapse_repeats()
+            .finish();
+
+        // The default format, which starts every line with a timestamp
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(handler)
+            .with_ansi(false)
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            for _ in 0..3 {
+                info!("retrying");
+                thread::sleep(Duration::from_millis(2));
+            }
+
+            // The same message from another callsite is not a repeat
+            info!("retrying");
+            info!("done");
+
+            let screen = terminal.screen();
+
+            assert!(screen[0].ends_with("INFO tracing_statusbar::unthreaded::tests: retrying (repeated 3 times)"));
+            assert!(screen[1].ends_with("INFO tracing_statusbar::unthreaded::tests: retrying"));
+            assert!(screen[2].ends_with("INFO tracing_statusbar::unthreaded::tests: done"));
+            assert_eq!(screen[3], "status");
+        });
+    }
+
+    #[test]
+    fn rate_limited_callsites_report_suppressed_entries() {
+        let terminal = VirtualTerminal::new(80, 5);
+
+        let handler = Builder::new(terminal.clone())
+            .with_callback(|output: &mut StatusWriter| write!(output, "status"))
+            .terminal_size(80, 5)
+            .force_terminal(true)
+            .on_finish(FinishMode::Clear)
+            .rate_limit(2, Duration::from_secs(60))
+            .finish();
+
+        // The note is written when the handler shuts down along with the subscriber
+        with_subscriber(handler, || {
+            for index in 1..=5 {
+                info!("flood {index}");
+            }
+
+            info!("other");
+
+            assert_eq!(terminal.contents(), "flood 1\nflood 2\nother\nstatus");
+        });
+
+        assert_eq!(
+            terminal.contents(),
+            "flood 1\nflood 2\nother\nsuppressed 3 similar messages from tracing_statusbar::unthreaded::tests",
+        );
+    }
+
+    #[test]
+    fn notifications_expire_without_log_messages() {
+        let terminal = VirtualTerminal::new(20, 5);
+
+        let handler = Builder::new(terminal.clone())
+            .with_callback(|output: &mut StatusWriter| write!(output, "status"))
+            .terminal_size(20, 5)
+            .force_terminal(true)
+            .on_finish(FinishMode::Clear)
+            .finish();
+
+        handler.handle().notify("config reloaded", Duration::from_millis(100)).unwrap();
+
+        assert_eq!(terminal.screen(), ["status", "config reloaded", "", "", ""]);
+
+        // Wait until the ticker thread has removed the notification
+        let deadline = Instant::now() + Duration::from_secs(5);
+
+        while !terminal.screen()[1].is_empty() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(terminal.screen(), ["status", "", "", "", ""]);
+
+        drop(handler);
+
+        assert_eq!(terminal.contents(), "");
+    }
+
+    #[test]
+    fn dropping_the_handler_after_a_panicking_refresh_does_not_panic() {
+        let terminal = VirtualTerminal::new(20, 5);
+        let refreshed = Arc::new(AtomicBool::new(false));
+
+        let handler = Builder::new(terminal.clone())
+            .with_callback({
+                let refreshed = refreshed.clone();
+
+                move |_: &mut StatusWriter| -> io::Result<()> {
+                    refreshed.store(true, Ordering::Relaxed);
+                    panic!("broken status line")
+                }
+            })
+            .terminal_size(20, 5)
+            .force_terminal(true)
+            .refresh_interval(Duration::from_millis(1))
+            .finish_with_threads();
+
+        while !refreshed.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        drop(handler);
+    }
+
+    #[test]
+    fn handles_report_a_panicking_callback() {
+        let terminal = VirtualTerminal::new(20, 5);
+
+        let handler = Builder::new(terminal.clone())
+            .with_callback(|_: &mut StatusWriter| panic!("broken status line"))
+            .terminal_size(20, 5)
+            .force_terminal(true)
+            .finish();
+
+        let handle = handler.handle();
+
+        assert!(thread::spawn(move || handler.make_writer().write_all(b"first\n")).join().is_err());
+        assert!(handle.redraw().is_err());
+        assert!(handle.notify("reloaded", Duration::from_secs(1)).is_err());
+    }
+
+    #[test]
+    fn pager_shows_the_history_and_holds_back_new_entries() {
+        let terminal = VirtualTerminal::new(40, 4);
+
+        let handler = Builder::new(terminal.clone())
+            .with_callback(|output: &mut StatusWriter| write!(output, "status"))
+            .terminal_size(40, 4)
+            .force_terminal(true)
+            .history(3)
+            .finish();
+
+        for index in 1..=5 {
+            handler.make_writer().write_all(format!("entry {index}\n").as_bytes()).unwrap();
+        }
+
+        let mut events = vec![Event::Key(KeyCode::Char('g').into()), Event::Key(KeyCode::Char('q').into())];
+
+        // Inspect the pager before every key press, writing a new entry after the first
+        let events = std::iter::from_fn(|| {
+            assert!(terminal.alternate_screen());
+
+            if events.len() == 2 {
+                assert_eq!(terminal.screen()[..3], ["entry 3", "entry 4", "entry 5"]);
+                handler.make_writer().write_all(b"entry 6\n").unwrap();
+            } else {
+                assert_eq!(terminal.screen()[..3], ["entry 3", "entry 4", "entry 5"]);
+                assert!(terminal.row(3).starts_with("Lines 1-3 of 4"));
+            }
+
+            (!events.is_empty()).then(|| Ok(events.remove(0)))
+        });
+
+        handler.handle().pager_with(events).unwrap();
+
+        assert!(!terminal.alternate_screen());
+        assert!(terminal.cursor_visible());
+        assert_eq!(terminal.contents(), "entry 1\nentry 2\nentry 3\nentry 4\nentry 5\nentry 6\nstatus");
+    }
 }
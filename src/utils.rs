@@ -1,3 +1,5 @@
+use std::env;
+
 use crossterm::terminal;
 
 /// A scope guard for terminal raw mode.
@@ -23,3 +25,29 @@ impl Drop for RawModeGuard {
             .expect("Could not enable terminal raw mode");
     }
 }
+
+/// Return true when the terminal is known to support synchronized updates (DEC private mode 2026).
+///
+/// There is no reliable way to query support without reading from the terminal, so this relies on
+/// the environment variables set by terminal emulators.
+pub fn supports_synchronized_output() -> bool {
+    const TERMS: &[&str] = &["alacritty", "contour", "foot", "ghostty", "kitty", "wezterm"];
+    const PROGRAMS: &[&str] = &["ghostty", "iTerm.app", "vscode", "WezTerm"];
+
+    if env::var_os("WT_SESSION").is_some() {
+        return true;
+    }
+
+    let term = env::var("TERM").unwrap_or_default();
+    let program = env::var("TERM_PROGRAM").unwrap_or_default();
+
+    TERMS.iter().any(|name| term.contains(name)) || PROGRAMS.contains(&program.as_str())
+}
+
+/// Return true when the provided target is equal to the provided module path prefix, or is a module
+/// below it. For example, the prefix `hyper` matches `hyper` and `hyper::client`, but not
+/// `hyper_util`.
+pub fn matches_target(target: &str, prefix: &str) -> bool {
+    target.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
@@ -0,0 +1,659 @@
+//! Ready-made building blocks for status lines.
+//!
+//! Every widget implements `MakeCallback`, so it can be passed directly to
+//! `Builder::with_callback`. Widgets can be combined horizontally using a `Row`, and rows can be
+//! stacked vertically using `Lines`.
+//!
+//! Widgets that show program state (such as `Counter`, `ProgressBar`, and `Text`) are cheap to
+//! clone, and all clones share the same state. A clone can be kept by the program to update the
+//! widget while the original is owned by the status line.
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crossterm::style::{ContentStyle, StyledContent};
+
+use crate::{RateEstimator, StatusWriter};
+use crate::format::{HumanByteRate, HumanDuration, HumanRate};
+
+/// A part of a status line.
+///
+/// Apart from `Lines`, widgets render themselves as a single line of text without a trailing
+/// newline. The trait is implemented for status line callbacks, so that custom segments can be
+/// mixed with the ready-made widgets.
+pub trait Widget: Send {
+    /// Render the widget into the provided writer.
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()>;
+}
+
+impl<F> Widget for F
+where
+    F: FnMut(&mut StatusWriter) -> io::Result<()> + Send,
+{
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        self(output)
+    }
+}
+
+impl Widget for Box<dyn Widget> {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        (**self).render(output)
+    }
+}
+
+/// Implement `MakeCallback` for widgets.
+macro_rules! impl_make_callback {
+    ($($widget:ty),* $(,)?) => {
+        $(
+            impl $crate::MakeCallback for $widget {
+                type Callback = Box<dyn FnMut(&mut $crate::StatusWriter) -> std::io::Result<()> + Send>;
+
+                fn make_callback(mut self) -> Self::Callback {
+                    Box::new(move |output| self.render(output))
+                }
+            }
+        )*
+    };
+}
+
+pub(crate) use impl_make_callback;
+
+impl_make_callback!(Text, Spinner, ProgressBar, Elapsed, Counter, Percent, Rate, Eta, Styled, Row, Lines);
+
+/// A piece of text.
+///
+/// The text can be changed at any time through a clone of the widget.
+#[derive(Clone)]
+pub struct Text(Arc<Mutex<String>>);
+
+impl Text {
+    /// Create a new text widget with the given initial text.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self(Arc::new(Mutex::new(text.into())))
+    }
+
+    /// Replace the text.
+    pub fn set(&self, text: impl Into<String>) {
+        *self.0.lock().expect("Text widget mutex was poisoned") = text.into();
+    }
+}
+
+impl Widget for Text {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        let text = self.0.lock().expect("Text widget mutex was poisoned");
+
+        write!(output, "{text}")
+    }
+}
+
+/// An animated spinner.
+///
+/// The frame that is shown depends on the time since the spinner was created, so the animation
+/// speed does not depend on how often the status line is redrawn. Use `Builder::refresh_interval`
+/// to keep the spinner moving when no log messages are written.
+pub struct Spinner {
+    /// The animation frames.
+    frames: &'static [&'static str],
+
+    /// The time each frame is shown.
+    interval: Duration,
+
+    /// The moment the animation started.
+    start: Instant,
+}
+
+impl Spinner {
+    /// The default animation frames.
+    pub const DEFAULT_FRAMES: &'static [&'static str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+    /// Animation frames that only use ASCII characters.
+    pub const ASCII_FRAMES: &'static [&'static str] = &["|", "/", "-", "\\"];
+
+    /// Create a new spinner using the default frames, showing each frame for 100 milliseconds.
+    pub fn new() -> Self {
+        Self {
+            frames: Self::DEFAULT_FRAMES,
+            interval: Duration::from_millis(100),
+            start: Instant::now(),
+        }
+    }
+
+    /// Use the provided animation frames.
+    pub fn frames(mut self, frames: &'static [&'static str]) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Show each frame for the provided duration.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl Default for Spinner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget for Spinner {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        if self.frames.is_empty() {
+            return Ok(());
+        }
+
+        let ticks = self.start.elapsed().as_nanos() / self.interval.as_nanos().max(1);
+        let frame = self.frames[(ticks % self.frames.len() as u128) as usize];
+
+        write!(output, "{frame}")
+    }
+}
+
+/// The shared state of a progress bar.
+#[derive(Default)]
+struct ProgressState {
+    /// The current position.
+    position: AtomicU64,
+
+    /// The position at which the progress is complete.
+    length: AtomicU64,
+}
+
+/// A determinate progress bar, e.g. `[=========>          ]`.
+///
+/// The position can be updated at any time through a clone of the widget.
+#[derive(Clone)]
+pub struct ProgressBar {
+    /// The shared progress state.
+    state: Arc<ProgressState>,
+
+    /// The width of the bar in characters, excluding the brackets.
+    width: usize,
+}
+
+impl ProgressBar {
+    /// Create a new progress bar for the given length. The bar is 30 characters wide by default.
+    pub fn new(length: u64) -> Self {
+        Self {
+            state: Arc::new(ProgressState {
+                position: AtomicU64::new(0),
+                length: AtomicU64::new(length),
+            }),
+            width: 30,
+        }
+    }
+
+    /// Set the width of the bar in characters, excluding the brackets.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Set the current position.
+    pub fn set_position(&self, position: u64) {
+        self.state.position.store(position, Ordering::Relaxed);
+    }
+
+    /// Advance the current position by the provided amount.
+    pub fn inc(&self, delta: u64) {
+        self.state.position.fetch_add(delta, Ordering::Relaxed);
+    }
+
+    /// Set the position at which the progress is complete.
+    pub fn set_length(&self, length: u64) {
+        self.state.length.store(length, Ordering::Relaxed);
+    }
+
+    /// Return the current position.
+    pub fn position(&self) -> u64 {
+        self.state.position.load(Ordering::Relaxed)
+    }
+
+    /// Return the position at which the progress is complete.
+    pub fn length(&self) -> u64 {
+        self.state.length.load(Ordering::Relaxed)
+    }
+
+    /// Return the completed fraction, between 0 and 1.
+    pub fn fraction(&self) -> f64 {
+        match self.length() {
+            0 => 0.0,
+            length => (self.position() as f64 / length as f64).clamp(0.0, 1.0),
+        }
+    }
+
+    /// Return a widget that shows the completed percentage of this progress bar.
+    pub fn percent(&self) -> Percent {
+        Percent(self.clone())
+    }
+
+    /// Return a widget that shows the rate at which this progress bar advances.
+    pub fn rate(&self) -> Rate {
+        Rate {
+            bar: self.clone(),
+            estimator: RateEstimator::new(),
+            bytes: false,
+        }
+    }
+
+    /// Return a widget that shows the estimated time until this progress bar is complete.
+    pub fn eta(&self) -> Eta {
+        Eta {
+            bar: self.clone(),
+            estimator: RateEstimator::new(),
+        }
+    }
+}
+
+impl Widget for ProgressBar {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        write!(output, "{}", bar(self.fraction(), self.width))
+    }
+}
+
+/// Return a progress bar of the given width for the provided completed fraction, including the
+/// brackets, e.g. `[=========>          ]`.
+pub(crate) fn bar(fraction: f64, width: usize) -> String {
+    let filled = (fraction * width as f64).round() as usize;
+
+    let bar = match filled {
+        0 => " ".repeat(width),
+        filled if filled >= width => "=".repeat(width),
+        filled => format!("{}>{}", "=".repeat(filled - 1), " ".repeat(width - filled)),
+    };
+
+    format!("[{bar}]")
+}
+
+/// The completed percentage of a progress bar, e.g. `42%`.
+///
+/// Created by calling `ProgressBar::percent`.
+#[derive(Clone)]
+pub struct Percent(ProgressBar);
+
+impl Widget for Percent {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        write!(output, "{:.0}%", self.0.fraction() * 100.0)
+    }
+}
+
+/// The rate at which a progress bar advances, e.g. `12.5/s`, or `1.5 MiB/s` for byte counts.
+///
+/// Created by calling `ProgressBar::rate`. The position of the bar is sampled every time the widget
+/// is rendered, so use `Builder::refresh_interval` to keep the rate up to date. A dash is shown
+/// until the rate can be estimated.
+pub struct Rate {
+    /// The progress bar whose rate is shown.
+    bar: ProgressBar,
+
+    /// The estimator that tracks the position of the bar.
+    estimator: RateEstimator,
+
+    /// Whether the position of the bar counts bytes.
+    bytes: bool,
+}
+
+impl Rate {
+    /// Show the rate in bytes per second, using binary units.
+    pub fn bytes(mut self) -> Self {
+        self.bytes = true;
+        self
+    }
+
+    /// Set the length of the window over which the rate is estimated. See `RateEstimator::window`.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.estimator = self.estimator.window(window);
+        self
+    }
+}
+
+impl Widget for Rate {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        self.estimator.update(self.bar.position());
+
+        match self.estimator.rate() {
+            Some(rate) if self.bytes => write!(output, "{}", HumanByteRate(rate)),
+            Some(rate) => write!(output, "{}", HumanRate(rate)),
+            None => write!(output, "-"),
+        }
+    }
+}
+
+/// The estimated time until a progress bar is complete, e.g. `3m 05s`.
+///
+/// Created by calling `ProgressBar::eta`. The position of the bar is sampled every time the widget
+/// is rendered, so use `Builder::refresh_interval` to keep the estimate up to date. A dash is shown
+/// while no progress is being made.
+pub struct Eta {
+    /// The progress bar whose remaining time is shown.
+    bar: ProgressBar,
+
+    /// The estimator that tracks the position of the bar.
+    estimator: RateEstimator,
+}
+
+impl Eta {
+    /// Set the length of the window over which the rate is estimated. See `RateEstimator::window`.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.estimator = self.estimator.window(window);
+        self
+    }
+}
+
+impl Widget for Eta {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        self.estimator.update(self.bar.position());
+
+        match self.estimator.eta(self.bar.length()) {
+            Some(eta) => write!(output, "{}", HumanDuration(eta)),
+            None => write!(output, "-"),
+        }
+    }
+}
+
+/// The time elapsed since the widget was created, e.g. `01:23` or `1:02:03`.
+pub struct Elapsed {
+    /// The moment from which the elapsed time is measured.
+    start: Instant,
+}
+
+impl Elapsed {
+    /// Create a new elapsed time widget, starting now.
+    pub fn new() -> Self {
+        Self::since(Instant::now())
+    }
+
+    /// Create a new elapsed time widget that measures from the provided moment.
+    pub fn since(start: Instant) -> Self {
+        Self {
+            start,
+        }
+    }
+}
+
+impl Default for Elapsed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget for Elapsed {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        let seconds = self.start.elapsed().as_secs();
+        let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+        if hours > 0 {
+            write!(output, "{hours}:{minutes:02}:{seconds:02}")
+        } else {
+            write!(output, "{minutes:02}:{seconds:02}")
+        }
+    }
+}
+
+/// A counter with an optional label, e.g. `12 files`.
+///
+/// The counter can be updated at any time through a clone of the widget.
+#[derive(Clone)]
+pub struct Counter {
+    /// The shared count.
+    count: Arc<AtomicU64>,
+
+    /// A label that is shown after the count.
+    label: Option<String>,
+}
+
+impl Counter {
+    /// Create a new counter, starting at zero.
+    pub fn new() -> Self {
+        Self {
+            count: Arc::new(AtomicU64::new(0)),
+            label: None,
+        }
+    }
+
+    /// Show the provided label after the count.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Increment the counter by one.
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Increment the counter by the provided amount.
+    pub fn add(&self, amount: u64) {
+        self.count.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    /// Set the counter to the provided value.
+    pub fn set(&self, count: u64) {
+        self.count.store(count, Ordering::Relaxed);
+    }
+
+    /// Return the current count.
+    pub fn get(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for Counter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget for Counter {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        match &self.label {
+            Some(label) => write!(output, "{} {label}", self.get()),
+            None => write!(output, "{}", self.get()),
+        }
+    }
+}
+
+/// A widget that renders another widget using a crossterm style.
+pub struct Styled {
+    /// The wrapped widget.
+    widget: Box<dyn Widget>,
+
+    /// The style used for rendering the widget.
+    style: ContentStyle,
+}
+
+impl Styled {
+    /// Wrap the provided widget so that it is rendered using the given style.
+    ///
+    /// Styles can be constructed using crossterm's `Stylize` trait, e.g.
+    /// `ContentStyle::new().yellow().bold()`.
+    pub fn new(widget: impl Widget + 'static, style: ContentStyle) -> Self {
+        Self {
+            widget: Box::new(widget),
+            style,
+        }
+    }
+}
+
+impl Widget for Styled {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        let mut buffer = output.nested();
+        self.widget.render(&mut buffer)?;
+
+        write!(output, "{}", StyledContent::new(self.style, String::from_utf8_lossy(buffer.as_bytes())))
+    }
+}
+
+/// A horizontal sequence of widgets, separated by a single space by default.
+pub struct Row {
+    /// The widgets in the row.
+    widgets: Vec<Box<dyn Widget>>,
+
+    /// The separator written between widgets.
+    separator: String,
+}
+
+impl Row {
+    /// Create a new empty row.
+    pub fn new() -> Self {
+        Self {
+            widgets: Vec::new(),
+            separator: " ".to_owned(),
+        }
+    }
+
+    /// Append a widget to the row.
+    pub fn push(mut self, widget: impl Widget + 'static) -> Self {
+        self.widgets.push(Box::new(widget));
+        self
+    }
+
+    /// Use the provided separator between widgets.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+}
+
+impl Default for Row {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget for Row {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        for (index, widget) in self.widgets.iter_mut().enumerate() {
+            if index > 0 {
+                write!(output, "{}", self.separator)?;
+            }
+
+            widget.render(output)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A vertical stack of widgets, each rendered on its own line.
+///
+/// Lines can be nested, and lines may wrap when they are wider than the terminal. The number of
+/// rows taken up by the status area is measured automatically. Widgets that render nothing do not
+/// take up a line, so that widgets such as `ProgressSpans` can grow and shrink the status area.
+pub struct Lines {
+    /// The widgets, one per line.
+    lines: Vec<Box<dyn Widget>>,
+}
+
+impl Lines {
+    /// Create a new empty stack of lines.
+    pub fn new() -> Self {
+        Self {
+            lines: Vec::new(),
+        }
+    }
+
+    /// Append a widget on a new line.
+    pub fn push(mut self, widget: impl Widget + 'static) -> Self {
+        self.lines.push(Box::new(widget));
+        self
+    }
+}
+
+impl Default for Lines {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget for Lines {
+    fn render(&mut self, output: &mut StatusWriter) -> io::Result<()> {
+        let mut first = true;
+
+        for line in &mut self.lines {
+            let mut nested = output.nested();
+            line.render(&mut nested)?;
+
+            if nested.as_bytes().is_empty() {
+                continue;
+            }
+
+            if !first {
+                writeln!(output)?;
+            }
+
+            output.write_all(nested.as_bytes())?;
+            first = false;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Render the provided widget into a string.
+    fn render(widget: &mut impl Widget) -> String {
+        let mut output = StatusWriter::new(80, 24);
+        widget.render(&mut output).unwrap();
+
+        String::from_utf8(output.as_bytes().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn progress_bars_handle_out_of_range_positions() {
+        // An empty length counts as no progress
+        let mut bar = ProgressBar::new(0).width(4);
+        bar.set_position(3);
+
+        assert_eq!(render(&mut bar), "[    ]");
+        assert_eq!(render(&mut bar.percent()), "0%");
+
+        // Positions beyond the length show a full bar
+        bar.set_length(10);
+        bar.set_position(15);
+
+        assert_eq!(render(&mut bar), "[====]");
+        assert_eq!(render(&mut bar.percent()), "100%");
+
+        bar.set_position(5);
+
+        assert_eq!(render(&mut bar), "[=>  ]");
+        assert_eq!(render(&mut bar.percent()), "50%");
+    }
+
+    #[test]
+    fn estimates_show_a_dash_until_they_are_known() {
+        let bar = ProgressBar::new(100);
+        bar.set_position(10);
+
+        // A single sample gives no rate, so the remaining time is unknown
+        assert_eq!(render(&mut bar.eta()), "-");
+        assert_eq!(render(&mut bar.rate()), "-");
+
+        // Nothing remains for an empty length
+        bar.set_length(0);
+
+        assert_eq!(render(&mut bar.eta()), "0s");
+    }
+
+    #[test]
+    fn rows_separate_their_widgets() {
+        assert_eq!(render(&mut Row::new()), "");
+        assert_eq!(render(&mut Row::new().push(Text::new("a"))), "a");
+        assert_eq!(render(&mut Row::new().push(Text::new("a")).push(Text::new("b"))), "a b");
+
+        let mut row = Row::new()
+            .push(Text::new("a"))
+            .push(Text::new("b"))
+            .push(Counter::new().label("files"))
+            .separator(" | ");
+
+        assert_eq!(render(&mut row), "a | b | 0 files");
+    }
+}